@@ -1,13 +1,18 @@
 use std::env;
 use std::fs;
 use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 fn main() {
     // Get the output directory
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
 
     // Determine which memory layout to use based on features
-    let memory_file = if cfg!(feature = "ble") {
+    let memory_file = if cfg!(feature = "board-dongle") {
+        // SoftDevice layout that stays clear of the Dongle's USB bootloader
+        "memory-dongle.x"
+    } else if cfg!(feature = "ble") {
         // Use SoftDevice memory layout for BLE app
         "memory-softdevice.x"
     } else if cfg!(feature = "gpio") {
@@ -24,6 +29,8 @@ fn main() {
     // Tell cargo to rerun this build script if memory files change
     println!("cargo:rerun-if-changed=memory-no-softdevice.x");
     println!("cargo:rerun-if-changed=memory-softdevice.x");
+    println!("cargo:rerun-if-changed=memory-dongle.x");
+    println!("cargo:rerun-if-changed=memory-gpio-with-softdevice.x");
     println!("cargo:rerun-if-changed=build.rs");
 
     // Tell cargo to look in the output directory for linker scripts
@@ -32,6 +39,125 @@ fn main() {
     // CRITICAL: Add --nmagic linker argument (link.x and defmt.x already in config.toml)
     println!("cargo:rustc-link-arg-bins=--nmagic");
 
+    // Embed version information for src/version.rs
+    println!("cargo:rustc-env=BUILD_MEMORY_LAYOUT={}", memory_file);
+    println!(
+        "cargo:rustc-env=BUILD_PROFILE={}",
+        env::var("PROFILE").unwrap_or_else(|_| "unknown".to_string())
+    );
+    emit_build_info();
+    emit_ram_layout(memory_file);
+
     // Print which memory layout is being used for debugging
     println!("cargo:warning=Using memory layout: {}", memory_file);
 }
+
+/// Export git hash, build time and enabled features as `env!` constants
+fn emit_build_info() {
+    let git_hash = Command::new("git")
+        .args(["describe", "--always", "--dirty", "--abbrev=8"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=BUILD_GIT_HASH={}", git_hash);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+
+    // Honour SOURCE_DATE_EPOCH for reproducible builds
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let epoch_secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", format_utc(epoch_secs));
+
+    // Cargo exposes each enabled feature as CARGO_FEATURE_<NAME>
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|name| name.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features.join(","));
+}
+
+/// Export the RAM region of the selected layout for src/mem_budget.rs
+fn emit_ram_layout(memory_file: &str) {
+    let layout = fs::read_to_string(memory_file).unwrap();
+    let ram = layout
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with("RAM"))
+        .unwrap_or_else(|| panic!("{} has no RAM region", memory_file));
+    let field = |name: &str| {
+        let start = ram.find(name).unwrap() + name.len();
+        let expr = ram[start..].trim_start_matches([' ', '=']);
+        let end = expr.find([',', '/']).unwrap_or(expr.len());
+        eval_size(&expr[..end])
+    };
+    println!("cargo:rustc-env=BUILD_RAM_ORIGIN={}", field("ORIGIN"));
+    println!("cargo:rustc-env=BUILD_RAM_LENGTH={}", field("LENGTH"));
+}
+
+/// Evaluate a linker size expression such as `0x20000000 + 31K` or `256K - 31K`
+fn eval_size(expr: &str) -> u64 {
+    let mut total: i64 = 0;
+    let mut sign = 1;
+    for token in expr.split_whitespace() {
+        match token {
+            "+" => sign = 1,
+            "-" => sign = -1,
+            _ => {
+                let (digits, scale) = match token.strip_suffix('K') {
+                    Some(digits) => (digits, 1024),
+                    None => match token.strip_suffix('M') {
+                        Some(digits) => (digits, 1024 * 1024),
+                        None => (token, 1),
+                    },
+                };
+                let value = match digits.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16),
+                    None => digits.parse::<i64>(),
+                }
+                .unwrap_or_else(|_| panic!("bad size expression: {}", expr));
+                total += sign * value * scale;
+            }
+        }
+    }
+    total as u64
+}
+
+/// Format seconds since the Unix epoch as "YYYY-MM-DD HH:MM UTC"
+fn format_utc(epoch_secs: u64) -> String {
+    let days = (epoch_secs / 86_400) as i64;
+    let secs_of_day = epoch_secs % 86_400;
+
+    // Civil-from-days (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        (secs_of_day % 3600) / 60
+    )
+}
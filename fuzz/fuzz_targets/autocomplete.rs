@@ -0,0 +1,14 @@
+//! Feed arbitrary partial input to TAB completion; it must never panic.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use nrf52840_dk_template::cli::CommandParser;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(partial) = core::str::from_utf8(data) {
+        for completion in CommandParser::autocomplete(partial) {
+            assert!(completion.starts_with(partial));
+        }
+    }
+});
@@ -0,0 +1,22 @@
+//! Feed arbitrary console lines to the CLI parser; it must never panic.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use nrf52840_dk_template::cli::{CliCommand, CommandParser};
+
+fuzz_target!(|data: &[u8]| {
+    // The terminal only ever hands valid UTF-8 lines to the parser
+    if let Ok(line) = core::str::from_utf8(data) {
+        match CommandParser::parse_command(line) {
+            // Wrapped command lines are parsed again when executed
+            CliCommand::Time(inner) => {
+                let _ = CommandParser::parse_command(&inner);
+            }
+            CliCommand::Watch(inner, _) => {
+                let _ = CommandParser::parse_command(&inner);
+            }
+            _ => {}
+        }
+    }
+});
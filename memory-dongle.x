@@ -0,0 +1,15 @@
+/* Memory layout for the nRF52840 Dongle (PCA10059) with SoftDevice S140 v7.3.0 */
+
+MEMORY
+{
+  /* NOTE 1 K = 1 KiBi = 1024 bytes */
+  /* The factory USB bootloader occupies 0xE0000 - 0x100000 and must not be overwritten */
+  /* The 32K below it is reserved for persistent storage (see src/storage.rs) */
+  FLASH : ORIGIN = 0x00000000 + 156K, LENGTH = 896K - 156K - 32K
+  RAM : ORIGIN = 0x20000000 + 31K, LENGTH = 256K - 31K
+}
+
+/* This is where the call stack will be allocated. */
+/* The stack is of the full descending type. */
+/* NOTE Do NOT modify `_stack_start` unless you know what you are doing */
+_stack_start = ORIGIN(RAM) + LENGTH(RAM);
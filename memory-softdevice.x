@@ -4,7 +4,8 @@ MEMORY
 {
   /* NOTE 1 K = 1 KiBi = 1024 bytes */
   /* NRF52840 with Softdevice S140 7.3.0 - from official examples */
-  FLASH : ORIGIN = 0x00000000 + 156K, LENGTH = 1024K - 156K
+  /* The last 32K of flash is reserved for persistent storage (see src/storage.rs) */
+  FLASH : ORIGIN = 0x00000000 + 156K, LENGTH = 1024K - 156K - 32K
   RAM : ORIGIN = 0x20000000 + 31K, LENGTH = 256K - 31K
 }
 
@@ -3,19 +3,13 @@
 
 use defmt::*;
 use embassy_executor::Spawner;
-#[allow(clippy::single_component_path_imports)]
-use embassy_nrf;
 use embassy_time::{Duration, Timer};
+use nrf52840_dk_template::state::APP_STATE;
+use nrf52840_dk_template::{init, status};
 use nrf_softdevice::ble::central;
-use nrf_softdevice::{raw, Softdevice};
+use nrf_softdevice::Softdevice;
 use {defmt_rtt as _, panic_halt as _};
 
-// SoftDevice task
-#[embassy_executor::task]
-async fn softdevice_task(sd: &'static Softdevice) -> ! {
-    sd.run().await
-}
-
 // BLE scanning task (separate from main to allow proper timing)
 #[embassy_executor::task]
 async fn ble_scan_task(sd: &'static Softdevice) {
@@ -24,6 +18,7 @@ async fn ble_scan_task(sd: &'static Softdevice) {
     Timer::after(Duration::from_secs(2)).await; // Give SoftDevice time to fully initialize
 
     info!("Beginning BLE scan...");
+    APP_STATE.update_stats(|s| s.ble_scans += 1);
     let config = central::ScanConfig {
         timeout: 30, // 30 second timeout
         ..Default::default()
@@ -53,64 +48,17 @@ async fn main(spawner: Spawner) {
 
     // Initialize Embassy FIRST with SoftDevice-compatible settings
     info!("Initializing Embassy...");
-    let mut embassy_config = embassy_nrf::config::Config::default();
-    embassy_config.gpiote_interrupt_priority = embassy_nrf::interrupt::Priority::P2;
-    embassy_config.time_interrupt_priority = embassy_nrf::interrupt::Priority::P2;
-    let _p = embassy_nrf::init(embassy_config);
+    let _p = init::init_embassy();
     info!("✅ Embassy initialized");
 
-    // Configure SoftDevice with the same config as working BLE+GPIO app
-    let config = nrf_softdevice::Config {
-        clock: Some(raw::nrf_clock_lf_cfg_t {
-            source: raw::NRF_CLOCK_LF_SRC_RC as u8,
-            rc_ctiv: 16,
-            rc_temp_ctiv: 2,
-            accuracy: raw::NRF_CLOCK_LF_ACCURACY_500_PPM as u8,
-        }),
-        conn_gap: Some(raw::ble_gap_conn_cfg_t {
-            conn_count: 6,
-            event_length: 6,
-        }),
-        conn_gatt: Some(raw::ble_gatt_conn_cfg_t { att_mtu: 256 }),
-        gatts_attr_tab_size: Some(raw::ble_gatts_cfg_attr_tab_size_t {
-            attr_tab_size: raw::BLE_GATTS_ATTR_TAB_SIZE_DEFAULT,
-        }),
-        gap_role_count: Some(raw::ble_gap_cfg_role_count_t {
-            adv_set_count: 1,
-            periph_role_count: 3,
-            central_role_count: 3,
-            central_sec_count: 0,
-            _bitfield_1: raw::ble_gap_cfg_role_count_t::new_bitfield_1(0),
-        }),
-        gap_device_name: Some(raw::ble_gap_cfg_device_name_t {
-            p_value: b"nRF52840-DK" as *const u8 as _,
-            current_len: 11,
-            max_len: 11,
-            write_perm: unsafe { core::mem::zeroed() },
-            _bitfield_1: raw::ble_gap_cfg_device_name_t::new_bitfield_1(
-                raw::BLE_GATTS_VLOC_STACK as u8,
-            ),
-        }),
-        ..Default::default()
-    };
-
-    info!("Enabling SoftDevice...");
-    let sd = Softdevice::enable(&config);
-    info!("✅ SoftDevice enabled successfully!");
-
-    info!("Spawning SoftDevice task...");
-    unwrap!(spawner.spawn(softdevice_task(sd)));
-    info!("✅ SoftDevice task spawned");
+    init::restore_config();
+    let sd = init::init_softdevice(spawner, "nRF52840-DK");
 
     info!("Spawning BLE scan task...");
     unwrap!(spawner.spawn(ble_scan_task(sd)));
     info!("✅ BLE scan task spawned");
 
-    info!("All systems operational - BLE scanner ready!");
+    unwrap!(status::spawn(spawner));
 
-    // Main loop - keep the app alive
-    loop {
-        info!("BLE Scanner running...");
-        Timer::after(Duration::from_millis(10000)).await;
-    }
+    info!("All systems operational - BLE scanner ready!");
 }
@@ -0,0 +1,140 @@
+#![no_std]
+#![no_main]
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_futures::join::join;
+use embassy_nrf::{
+    bind_interrupts,
+    gpio::{Level, Output, OutputDrive},
+    pac, peripherals,
+    usb::{self, vbus_detect::SoftwareVbusDetect, Driver},
+};
+use embassy_usb::class::cdc_acm::{CdcAcmClass, State};
+use embassy_usb::Builder;
+use nrf_softdevice::raw;
+use {defmt_rtt as _, panic_halt as _};
+
+use nrf52840_dk_template::board_leds;
+use nrf52840_dk_template::cli::{session, CommandHandler, Terminal, UsbSerial};
+use nrf52840_dk_template::init;
+use nrf52840_dk_template::log_transfer;
+use nrf52840_dk_template::storage::{Counter, Storage};
+use nrf52840_dk_template::system::{self, BootMode};
+
+/// GAP device name and prompt until one is set with `bt_name`
+const HOSTNAME: &str = "nRF52840 Dongle CLI";
+
+bind_interrupts!(struct Irqs {
+    USBD => usb::InterruptHandler<peripherals::USBD>;
+});
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    // Initialize Embassy FIRST with SoftDevice-compatible settings
+    info!("Initializing Embassy with SoftDevice-compatible settings...");
+    let p = init::init_embassy();
+    info!("✅ Embassy initialized");
+
+    // Check for a safe-mode request before the SoftDevice takes over POWER
+    let boot_mode = system::take_boot_mode();
+    let safe_mode = boot_mode == BootMode::Safe;
+    if safe_mode {
+        warn!("SAFE MODE: SoftDevice and BLE disabled");
+    }
+
+    // Restore saved settings; flash is read directly, before the SoftDevice owns it
+    init::restore_config();
+
+    // Enable SoftDevice AFTER Embassy initialization
+    let sd = if safe_mode {
+        // USB needs the crystal oscillator; without the SoftDevice we start it ourselves
+        pac::CLOCK.tasks_hfclkstart().write_value(1);
+        None
+    } else {
+        let sd = init::init_softdevice(spawner, HOSTNAME);
+        // USB needs the crystal oscillator, which the SoftDevice now controls
+        unsafe {
+            raw::sd_clock_hfclk_request();
+        }
+        Some(sd)
+    };
+
+    // Configure peripherals AFTER SoftDevice is enabled
+    info!("Configuring peripherals...");
+    let leds = board_leds!(p);
+
+    // LD1 flashes on RX, LD2 red on TX, LD2 green/blue are the CLI's LED 3/4
+    let led1 = Output::new(leds.led1, Level::High, OutputDrive::Standard);
+    let led2 = Output::new(leds.led2, Level::High, OutputDrive::Standard);
+    let led3 = Output::new(leds.led3, Level::High, OutputDrive::Standard);
+    let led4 = Output::new(leds.led4, Level::High, OutputDrive::Standard);
+
+    // The Dongle is bus powered, so VBUS is always present while it runs.
+    // The POWER interrupt belongs to the SoftDevice, so use software detection.
+    let vbus = SoftwareVbusDetect::new(true, true);
+    let driver = Driver::new(p.USBD, Irqs, &vbus);
+
+    // Test VID/PID, replace before distributing devices
+    let mut usb_config = embassy_usb::Config::new(0xc0de, 0xcafe);
+    usb_config.manufacturer = Some("nRF52840-DK-rust");
+    usb_config.product = Some("nRF52840 Dongle CLI");
+    usb_config.max_power = 100;
+    usb_config.max_packet_size_0 = 64;
+
+    let mut config_descriptor = [0; 256];
+    let mut bos_descriptor = [0; 256];
+    let mut msos_descriptor = [0; 256];
+    let mut control_buf = [0; 64];
+    let mut cdc_state = State::new();
+
+    let mut builder = Builder::new(
+        driver,
+        usb_config,
+        &mut config_descriptor,
+        &mut bos_descriptor,
+        &mut msos_descriptor,
+        &mut control_buf,
+    );
+    let class = CdcAcmClass::new(&mut builder, &mut cdc_state, 64);
+    let mut usb = builder.build();
+    info!("✅ Peripherals configured");
+
+    let mut command_handler = CommandHandler::new().with_leds(led3, led4);
+    if let Some(sd) = sd {
+        // Repair anything a power loss left half-written before using it
+        let mut storage = Storage::new(sd);
+        storage.recover().await;
+        if let Ok(boots) = storage.add_to_counter(Counter::PowerCycles, 1).await {
+            info!("Power cycle {}", boots);
+        }
+        command_handler = command_handler.with_softdevice(sd).with_storage(storage);
+        unwrap!(spawner.spawn(log_transfer::transfer_task(sd)));
+    }
+
+    let cli = async {
+        // Output written before a host opens the port would be lost
+        let mut serial = UsbSerial::new(class);
+        serial.wait_connection().await;
+        info!("USB host connected");
+
+        let mut terminal = Terminal::new(serial)
+            .with_hostname(HOSTNAME)
+            .with_tx_led(led2);
+        let _ = terminal.write_line("").await;
+        let _ = terminal.write_line("nRF52840 Dongle CLI Interface").await;
+        if safe_mode {
+            let _ = terminal
+                .write_line("SAFE MODE - BLE disabled, use 'reset' to restart normally")
+                .await;
+        }
+        let _ = terminal
+            .write_line("Type 'help' for available commands")
+            .await;
+        let _ = terminal.print_prompt().await;
+
+        session::run(&mut terminal, &mut command_handler, Some(led1)).await
+    };
+
+    join(usb.run(), cli).await;
+}
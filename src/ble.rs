@@ -0,0 +1,27 @@
+//! BLE identity helpers
+//!
+//! Runtime control of the GAP device name and static random address on top
+//! of the SoftDevice.
+
+use crate::state::MAX_DEVICE_NAME_LEN;
+use nrf_softdevice::ble::{self, Address, AddressType};
+use nrf_softdevice::{raw, RawError, Softdevice};
+
+/// Set the GAP device name (not writable by peers)
+pub fn set_device_name(_sd: &Softdevice, name: &str) -> Result<(), RawError> {
+    let len = name.len().min(MAX_DEVICE_NAME_LEN);
+    // No access for peers: security mode 0, level 0
+    let write_perm: raw::ble_gap_conn_sec_mode_t = unsafe { core::mem::zeroed() };
+    let ret = unsafe { raw::sd_ble_gap_device_name_set(&write_perm, name.as_ptr(), len as u16) };
+    RawError::convert(ret)
+}
+
+/// Use `addr` (LSB first) as the static random device address
+pub fn set_static_address(sd: &Softdevice, addr: [u8; 6]) {
+    ble::set_address(sd, &Address::new(AddressType::RandomStatic, addr));
+}
+
+/// Current device address (LSB first)
+pub fn address(sd: &Softdevice) -> [u8; 6] {
+    ble::get_address(sd).bytes()
+}
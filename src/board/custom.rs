@@ -0,0 +1,75 @@
+//! Custom carrier board
+//!
+//! Starting point for in-house carrier boards. The assignments below mirror
+//! the nRF52840-DK; change the pin types and the macro bodies together to
+//! match the carrier's schematic. LEDs and buttons are expected to be
+//! active low, as on the DK.
+
+use embassy_nrf::peripherals::{
+    P0_11, P0_12, P0_13, P0_14, P0_15, P0_16, P0_24, P0_25, P1_14, P1_15,
+};
+
+pub const NAME: &str = "custom carrier";
+
+/// Console link as shown by `status`
+pub const CONSOLE: &str = "UART 115200 baud on P1.14/P1.15";
+
+/// Pins (port * 32 + pin) wired to on-board hardware: 32 kHz crystal,
+/// LEDs, buttons, reset and the console UART
+pub const RESERVED_PINS: &[u8] = &[0, 1, 11, 12, 13, 14, 15, 16, 18, 24, 25, 46, 47];
+
+pub struct Leds {
+    pub led1: P0_13,
+    pub led2: P0_14,
+    pub led3: P0_15,
+    pub led4: P0_16,
+}
+
+pub struct Buttons {
+    pub button1: P0_11,
+    pub button2: P0_12,
+    pub button3: P0_24,
+    pub button4: P0_25,
+}
+
+pub struct ConsoleUart {
+    pub rx: P1_14,
+    pub tx: P1_15,
+}
+
+/// Move the LED pins out of `embassy_nrf::Peripherals`
+#[macro_export]
+macro_rules! board_leds {
+    ($p:ident) => {
+        $crate::board::Leds {
+            led1: $p.P0_13,
+            led2: $p.P0_14,
+            led3: $p.P0_15,
+            led4: $p.P0_16,
+        }
+    };
+}
+
+/// Move the button pins out of `embassy_nrf::Peripherals`
+#[macro_export]
+macro_rules! board_buttons {
+    ($p:ident) => {
+        $crate::board::Buttons {
+            button1: $p.P0_11,
+            button2: $p.P0_12,
+            button3: $p.P0_24,
+            button4: $p.P0_25,
+        }
+    };
+}
+
+/// Move the console UART pins out of `embassy_nrf::Peripherals`
+#[macro_export]
+macro_rules! board_console_uart {
+    ($p:ident) => {
+        $crate::board::ConsoleUart {
+            rx: $p.P1_14,
+            tx: $p.P1_15,
+        }
+    };
+}
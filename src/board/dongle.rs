@@ -0,0 +1,41 @@
+//! nRF52840 Dongle (PCA10059)
+//!
+//! LD1 (green) and the RGB LED LD2 are active low and stand in for the DK's
+//! four LEDs. The user button is not mapped, and the console runs over USB
+//! CDC-ACM because there is no UART bridge.
+
+use embassy_nrf::peripherals::{P0_06, P0_08, P0_12, P1_09};
+
+pub const NAME: &str = "nRF52840 Dongle";
+
+/// Console link as shown by `status`
+pub const CONSOLE: &str = "USB CDC-ACM";
+
+/// Pins (port * 32 + pin) wired to on-board hardware: LEDs, the user
+/// button (P1.06) and reset
+pub const RESERVED_PINS: &[u8] = &[6, 8, 12, 18, 38, 41];
+
+/// Indicator roles mapped onto LD1 and the channels of LD2
+pub struct Leds {
+    /// LD1 green: heartbeat / RX activity
+    pub led1: P0_06,
+    /// LD2 red: TX activity
+    pub led2: P0_08,
+    /// LD2 green: CLI controllable
+    pub led3: P1_09,
+    /// LD2 blue: CLI controllable
+    pub led4: P0_12,
+}
+
+/// Move the LED pins out of `embassy_nrf::Peripherals`
+#[macro_export]
+macro_rules! board_leds {
+    ($p:ident) => {
+        $crate::board::Leds {
+            led1: $p.P0_06,
+            led2: $p.P0_08,
+            led3: $p.P1_09,
+            led4: $p.P0_12,
+        }
+    };
+}
@@ -0,0 +1,34 @@
+//! Board pin definitions
+//!
+//! Each supported board has a module naming the pins used for its LEDs,
+//! buttons and console UART. Binaries take their pins through the
+//! `board_leds!`, `board_buttons!` and `board_console_uart!` macros instead
+//! of hard-coding `P0_xx` fields, so porting to a new board only touches
+//! this directory.
+//!
+//! The nRF52840-DK mapping is used unless another board feature is enabled:
+//! - `board-custom`: custom carrier board (see custom.rs)
+//! - `board-dongle`: nRF52840 Dongle, USB console only (see dongle.rs)
+
+#[cfg(all(feature = "board-custom", feature = "board-dongle"))]
+compile_error!("enable at most one board-* feature");
+
+#[cfg(not(any(feature = "board-custom", feature = "board-dongle")))]
+mod dk;
+#[cfg(not(any(feature = "board-custom", feature = "board-dongle")))]
+pub use dk::*;
+
+#[cfg(feature = "board-custom")]
+mod custom;
+#[cfg(feature = "board-custom")]
+pub use custom::*;
+
+#[cfg(feature = "board-dongle")]
+mod dongle;
+#[cfg(feature = "board-dongle")]
+pub use dongle::*;
+
+/// Whether `pin_port` (port * 32 + pin) is wired to something on this board
+pub fn is_reserved_pin(pin_port: u8) -> bool {
+    RESERVED_PINS.contains(&pin_port)
+}
@@ -0,0 +1,203 @@
+//! Typed argument extraction for the parser
+//!
+//! [`Args`] walks the words after the command name. Each extractor either
+//! returns a checked value or an [`ArgError`] naming the command and quoting
+//! the argument that was rejected, so every command reports bad input the
+//! same way.
+
+use super::registry;
+use core::ops::RangeInclusive;
+use core::str::FromStr;
+use heapless::String;
+
+/// Longest rejected argument quoted back in an error
+pub const MAX_QUOTED_ARG: usize = 16;
+
+/// What was wrong with an argument
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum ArgProblem {
+    /// A required argument is absent; holds its name from the usage
+    Missing(&'static str),
+    /// Not a number
+    NotANumber,
+    /// A number outside the inclusive range
+    OutOfRange(u32, u32),
+    /// Not one of the words the usage lists
+    NotAChoice,
+    /// Text longer than this many bytes
+    TooLong(usize),
+    /// Well-formed but not acceptable, for the given reason
+    Invalid(&'static str),
+    /// A word after the last argument the command takes
+    Unexpected,
+}
+
+/// A rejected command line
+#[derive(Debug, Clone)]
+pub struct ArgError {
+    /// Registry name of the command
+    pub command: &'static str,
+    /// The offending argument, truncated to [`MAX_QUOTED_ARG`] bytes
+    pub arg: Option<String<MAX_QUOTED_ARG>>,
+    pub problem: ArgProblem,
+}
+
+impl ArgError {
+    pub fn new(command: &'static str, arg: Option<&str>, problem: ArgProblem) -> Self {
+        let arg = arg.map(|arg| {
+            let mut quoted = String::new();
+            for ch in arg.chars() {
+                if quoted.push(ch).is_err() {
+                    break;
+                }
+            }
+            quoted
+        });
+        Self {
+            command,
+            arg,
+            problem,
+        }
+    }
+
+    /// The command's argument synopsis, for a usage hint
+    pub fn usage(&self) -> &'static str {
+        registry::find(self.command).map_or("", |spec| spec.args)
+    }
+}
+
+/// Cursor over the arguments of one command line
+pub struct Args<'a> {
+    command: &'static str,
+    rest: &'a str,
+}
+
+impl<'a> Args<'a> {
+    /// `rest` is the line after the command name
+    pub fn new(command: &'static str, rest: &'a str) -> Self {
+        Self { command, rest }
+    }
+
+    /// Registry name of the command being parsed
+    pub fn command(&self) -> &'static str {
+        self.command
+    }
+
+    /// An error for this command, quoting `arg`
+    pub fn error(&self, arg: Option<&str>, problem: ArgProblem) -> ArgError {
+        ArgError::new(self.command, arg, problem)
+    }
+
+    /// The next whitespace-separated word
+    pub fn next_word(&mut self) -> Option<&'a str> {
+        let rest = self.rest.trim_start();
+        if rest.is_empty() {
+            self.rest = rest;
+            return None;
+        }
+        let (word, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        self.rest = tail;
+        Some(word)
+    }
+
+    /// The next word without consuming it
+    pub fn peek(&self) -> Option<&'a str> {
+        self.rest.split_whitespace().next()
+    }
+
+    /// Everything not consumed yet, trimmed
+    pub fn remainder(&self) -> &'a str {
+        self.rest.trim()
+    }
+
+    /// Everything not consumed yet, trimmed, consuming it
+    pub fn take_remainder(&mut self) -> &'a str {
+        let rest = self.remainder();
+        self.rest = "";
+        rest
+    }
+
+    /// Check that every word has been consumed
+    pub fn finish(&mut self) -> Result<(), ArgError> {
+        match self.next_word() {
+            Some(word) => Err(self.error(Some(word), ArgProblem::Unexpected)),
+            None => Ok(()),
+        }
+    }
+
+    /// A required number in `range`
+    pub fn number<T>(&mut self, name: &'static str, range: RangeInclusive<T>) -> Result<T, ArgError>
+    where
+        T: FromStr + PartialOrd + Copy + Into<u32>,
+    {
+        match self.next_word() {
+            Some(word) => self.check_number(word, range),
+            None => Err(self.error(None, ArgProblem::Missing(name))),
+        }
+    }
+
+    /// An optional number in `range`, `None` if there are no more words
+    pub fn opt_number<T>(&mut self, range: RangeInclusive<T>) -> Result<Option<T>, ArgError>
+    where
+        T: FromStr + PartialOrd + Copy + Into<u32>,
+    {
+        self.next_word()
+            .map(|word| self.check_number(word, range))
+            .transpose()
+    }
+
+    fn check_number<T>(&self, word: &str, range: RangeInclusive<T>) -> Result<T, ArgError>
+    where
+        T: FromStr + PartialOrd + Copy + Into<u32>,
+    {
+        let value = word
+            .parse::<T>()
+            .map_err(|_| self.error(Some(word), ArgProblem::NotANumber))?;
+        if range.contains(&value) {
+            Ok(value)
+        } else {
+            let problem = ArgProblem::OutOfRange((*range.start()).into(), (*range.end()).into());
+            Err(self.error(Some(word), problem))
+        }
+    }
+
+    /// A word mapped through `choices`, `None` if there are no more words
+    pub fn opt_choice<V: Copy>(&mut self, choices: &[(&str, V)]) -> Result<Option<V>, ArgError> {
+        let Some(word) = self.next_word() else {
+            return Ok(None);
+        };
+        choices
+            .iter()
+            .find(|(choice, _)| *choice == word)
+            .map(|&(_, value)| Some(value))
+            .ok_or_else(|| self.error(Some(word), ArgProblem::NotAChoice))
+    }
+
+    /// The rest of the line as free text, `None` if empty
+    ///
+    /// Inner spaces are kept. A value wrapped in double quotes has them
+    /// removed, which is the way to give text with leading or trailing spaces.
+    pub fn opt_text<const N: usize>(&mut self) -> Result<Option<String<N>>, ArgError> {
+        let rest = self.take_remainder();
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        let text = rest
+            .strip_prefix('"')
+            .and_then(|inner| inner.strip_suffix('"'))
+            .unwrap_or(rest);
+        let mut value = String::new();
+        match value.push_str(text) {
+            Ok(()) => Ok(Some(value)),
+            Err(()) => Err(self.error(None, ArgProblem::TooLong(N))),
+        }
+    }
+
+    /// The rest of the line as required free text, see [`Args::opt_text`]
+    pub fn text<const N: usize>(&mut self, name: &'static str) -> Result<String<N>, ArgError> {
+        match self.opt_text()? {
+            Some(text) => Ok(text),
+            None => Err(self.error(None, ArgProblem::Missing(name))),
+        }
+    }
+}
@@ -1,15 +1,47 @@
-use super::{CliCommand, CliError};
-use cortex_m::peripheral::SCB;
-use defmt::info;
-use embassy_nrf::gpio::{Input, Output};
-use embassy_time::Instant;
+use super::args::{ArgError, ArgProblem};
+use super::output::{self, Color};
+use super::parser::CommandParser;
+use super::strings::Msg;
+use super::{
+    AddrSetting, CaptureView, CliCommand, CliError, MetaField, MetaSetting, ResetMode,
+    ScanCfgSetting, SessionLogSetting, CLI_BUFFER_SIZE, MAX_HISTORY_SIZE, MAX_SCAN_RESULTS,
+    RESPONSE_SIZE, SWUART_CAPTURE_SIZE,
+};
+use crate::ble;
+use crate::board;
+use crate::clock::{self, DateTime};
+use crate::fmt_util::{
+    write_datetime, write_escaped, write_fixed, write_hex_byte, write_num, write_percent,
+    write_temperature,
+};
+use crate::led::{LedId, LED_COUNT};
+use crate::mem_budget;
+use crate::pinspect;
+use crate::state::{AppState, Config, LogMode, APP_STATE, MAX_META_LEN};
+use crate::storage::{self, Counter, LogDirection, Storage};
+use crate::swuart;
+use crate::system;
+use crate::version;
+use defmt::{info, warn};
+use embassy_nrf::gpio::{AnyPin, Input, Output, Pull};
+use embassy_time::{Duration, Instant};
 use heapless::String;
 use nrf_softdevice::ble::central;
 use nrf_softdevice::Softdevice;
 
+/// Longest "  ... and N more\r\n" after a truncated scan listing
+const SCAN_TRAILER_LEN: usize = 19;
+
+/// Room for the `Elapsed: N ms` line `time` appends, color escapes included
+const ELAPSED_LINE_SIZE: usize = 48;
+
+/// Lifetime counts held in RAM before they are written to flash
+const COUNTER_FLUSH_COUNT: u32 = 16;
+/// Longest a lifetime count waits in RAM once another count arrives
+const COUNTER_FLUSH_INTERVAL: Duration = Duration::from_secs(600);
+
 pub struct CommandHandler<'d> {
-    led_states: [bool; 4],
-    start_time: Instant,
+    led_states: [bool; LED_COUNT],
     led3: Option<Output<'d>>,
     led4: Option<Output<'d>>,
     button1: Option<Input<'d>>,
@@ -17,6 +49,10 @@ pub struct CommandHandler<'d> {
     button3: Option<Input<'d>>,
     button4: Option<Input<'d>>,
     softdevice: Option<&'d Softdevice>,
+    storage: Option<Storage>,
+    /// Lifetime counts not yet written to flash
+    pending_counts: [u32; Counter::COUNT],
+    last_counter_flush: Instant,
 }
 
 impl<'d> Default for CommandHandler<'d> {
@@ -28,8 +64,7 @@ impl<'d> Default for CommandHandler<'d> {
 impl<'d> CommandHandler<'d> {
     pub fn new() -> Self {
         Self {
-            led_states: [false; 4],
-            start_time: Instant::now(),
+            led_states: [false; LED_COUNT],
             led3: None,
             led4: None,
             button1: None,
@@ -37,6 +72,9 @@ impl<'d> CommandHandler<'d> {
             button3: None,
             button4: None,
             softdevice: None,
+            storage: None,
+            pending_counts: [0; Counter::COUNT],
+            last_counter_flush: Instant::now(),
         }
     }
 
@@ -65,10 +103,87 @@ impl<'d> CommandHandler<'d> {
         self
     }
 
+    pub fn with_storage(mut self, storage: Storage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
     pub async fn execute_command(
         &mut self,
         command: CliCommand,
-    ) -> Result<heapless::String<256>, CliError> {
+    ) -> Result<String<RESPONSE_SIZE>, CliError> {
+        match command {
+            CliCommand::Time(command_line) => self.execute_timed(&command_line).await,
+            command => {
+                self.count_command(&command).await;
+                self.run_command(command).await
+            }
+        }
+    }
+
+    /// Run a command again for `watch`, without counting it as typed
+    pub async fn refresh_command(
+        &mut self,
+        command: CliCommand,
+    ) -> Result<String<RESPONSE_SIZE>, CliError> {
+        self.run_command(command).await
+    }
+
+    async fn execute_timed(
+        &mut self,
+        command_line: &str,
+    ) -> Result<String<RESPONSE_SIZE>, CliError> {
+        let command = CommandParser::parse_command(command_line);
+
+        // Commands that need the terminal or never return can't be timed here
+        if matches!(
+            command,
+            CliCommand::Empty
+                | CliCommand::Help
+                | CliCommand::Clear
+                | CliCommand::Reset(_)
+                | CliCommand::Watch(_, _)
+                | CliCommand::BenchUart(_, _)
+                | CliCommand::EchoHex(_)
+                | CliCommand::Loopback(_)
+                | CliCommand::SessionDump
+                | CliCommand::Time(_)
+        ) {
+            let mut response = String::new();
+            let _ = response.push_str("time: this command can't be timed");
+            return Ok(response);
+        }
+
+        self.count_command(&command).await;
+        let start = Instant::now();
+        let mut response = self.run_command(command).await?;
+        let elapsed_us = (Instant::now() - start).as_micros();
+        info!("CLI: '{}' took {} us", command_line, elapsed_us);
+
+        let mut elapsed: String<ELAPSED_LINE_SIZE> = String::new();
+        if !response.is_empty() {
+            let _ = elapsed.push_str("\r\n");
+        }
+        let _ = output::push_colored(&mut elapsed, "Elapsed:", Color::Bold);
+        let _ = elapsed.push(' ');
+        let _ = write_fixed(&mut elapsed, elapsed_us as i64, 3);
+        let _ = elapsed.push_str(" ms");
+
+        // The timing is the point of `time`, so cut the command's output
+        // short rather than the timing line
+        let mut keep = response.len().min(RESPONSE_SIZE - elapsed.len());
+        while !response.is_char_boundary(keep) {
+            keep -= 1;
+        }
+        response.truncate(keep);
+        let _ = response.push_str(&elapsed);
+        Ok(response)
+    }
+
+    async fn run_command(
+        &mut self,
+        command: CliCommand,
+    ) -> Result<String<RESPONSE_SIZE>, CliError> {
         let mut response = heapless::String::new();
 
         match command {
@@ -78,32 +193,72 @@ impl<'d> CommandHandler<'d> {
             }
             CliCommand::Help => {
                 // Help is handled in terminal.rs
-                let _ = response.push_str("Help displayed");
+                let _ = response.push_str(Msg::HelpDisplayed.text());
             }
             CliCommand::Version => {
                 info!("CLI: Version requested");
-                let _ = response.push_str("nRF52840-DK CLI v1.0.0");
+                let _ = response.push_str(version::FIRMWARE);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Built");
+                let _ = response.push_str(version::BUILD_TIMESTAMP);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Features");
+                let _ = response.push_str(version::FEATURES);
+            }
+            CliCommand::BuildInfo => {
+                info!("CLI: Build info requested");
+                let _ = output::push_heading(&mut response, "Build Info:");
+                let _ = output::push_label(&mut response, "Board");
+                let _ = response.push_str(board::NAME);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Memory");
+                let _ = response.push_str(version::MEMORY_LAYOUT);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Profile");
+                let _ = response.push_str(version::PROFILE);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Features");
+                let _ = response.push_str(version::FEATURES);
             }
             CliCommand::Status => {
                 info!("CLI: Status requested");
-                let _ = response.push_str("System Status:\r\n");
-                let _ = response.push_str("  Firmware: nRF52840-DK CLI v1.0.0\r\n");
-                let _ = response.push_str("  UART: 115200 baud on pins P1.14/P1.15\r\n");
-                let _ = response.push_str("  LEDs: ");
+                let _ = output::push_heading(&mut response, "System Status:");
+                let _ = output::push_label(&mut response, "Firmware");
+                let _ = response.push_str(version::FIRMWARE);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Console");
+                let _ = response.push_str(board::CONSOLE);
+                let _ = response.push_str("\r\n");
+                let console = APP_STATE.stats().console;
+                let _ = output::push_label(&mut response, "Line errors");
+                let _ = write_num(&mut response, console.total() as u64);
+                if console.total() > 0 {
+                    let _ = response.push_str(" (F");
+                    let _ = write_num(&mut response, console.framing as u64);
+                    let _ = response.push_str(" O");
+                    let _ = write_num(&mut response, console.overrun as u64);
+                    let _ = response.push_str(")");
+                }
+                let _ = response.push_str(", re-inits ");
+                let _ = write_num(&mut response, console.reinits as u64);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Supply");
+                self.push_supply(&mut response);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "LEDs");
                 let _ = response.push_str("3:");
-                let _ = response.push_str(if self.led_states[2] { "on " } else { "off " });
-                let _ = response.push_str("4:");
-                let _ = response.push_str(if self.led_states[3] { "on" } else { "off" });
+                let _ = output::push_on_off(&mut response, self.led_states[2]);
+                let _ = response.push_str(" 4:");
+                let _ = output::push_on_off(&mut response, self.led_states[3]);
             }
             CliCommand::Uptime => {
                 info!("CLI: Uptime requested");
-                let uptime = Instant::now() - self.start_time;
-                let uptime_secs = uptime.as_secs();
+                let uptime_secs = clock::uptime().as_secs();
                 let hours = uptime_secs / 3600;
                 let minutes = (uptime_secs % 3600) / 60;
                 let seconds = uptime_secs % 60;
 
-                let _ = response.push_str("Uptime: ");
+                let _ = response.push_str(Msg::UptimeLabel.text());
                 if hours > 0 {
                     let _ = write_num(&mut response, hours);
                     let _ = response.push_str("h ");
@@ -115,73 +270,66 @@ impl<'d> CommandHandler<'d> {
                 let _ = write_num(&mut response, seconds);
                 let _ = response.push_str("s");
             }
+            CliCommand::Date(setting) => {
+                if let Some(unix_secs) = setting {
+                    info!("CLI: Wall clock set to {}", unix_secs);
+                    clock::set_unix_time(unix_secs as u64);
+                }
+                let _ = response.push_str(Msg::DateLabel.text());
+                match clock::unix_time() {
+                    Some(now) => {
+                        let _ = write_datetime(&mut response, &DateTime::from_unix(now));
+                        let _ = response.push_str(" UTC (");
+                        let _ = write_num(&mut response, now);
+                        let _ = response.push(')');
+                    }
+                    None => {
+                        let _ = response.push_str("not set, use 'date <unix secs>'");
+                    }
+                }
+            }
             CliCommand::Clear => {
                 // Clear is handled in terminal.rs
-                let _ = response.push_str("Screen cleared");
+                let _ = response.push_str(Msg::ScreenCleared.text());
             }
-            CliCommand::Reset => {
+            CliCommand::Reset(mode) => {
                 info!("CLI: Reset requested");
-                let _ = response.push_str("Resetting system...");
-                // Perform system reset using cortex-m
-                SCB::sys_reset();
+                // The caller writes this response, then performs the reset
+                match mode {
+                    ResetMode::Now => system::immediate_reset(),
+                    ResetMode::Graceful => {
+                        let _ = response.push_str(Msg::Resetting.text());
+                    }
+                    ResetMode::Bootloader => {
+                        let _ = response.push_str(Msg::ResettingBootloader.text());
+                    }
+                    ResetMode::Safe => {
+                        let _ = response.push_str(Msg::ResettingSafe.text());
+                    }
+                }
             }
             CliCommand::Echo(text) => {
                 info!("CLI: Echo requested: {}", text.as_str());
                 let _ = response.push_str(&text);
             }
-            CliCommand::LedOn(led_num) => {
-                info!("CLI: LED {} on requested", led_num);
-                let idx = (led_num - 1) as usize;
-                if idx < 4 {
-                    self.led_states[idx] = true;
-                    let _ = response.push_str("LED ");
-                    let _ = response.push((led_num + b'0') as char);
-                    let _ = response.push_str(" turned on");
-
-                    // Actually control the LED hardware
-                    match led_num {
-                        3 => {
-                            if let Some(ref mut led) = self.led3 {
-                                led.set_low(); // LEDs are active low
-                            }
-                        }
-                        4 => {
-                            if let Some(ref mut led) = self.led4 {
-                                led.set_low(); // LEDs are active low
-                            }
-                        }
-                        _ => {}
-                    }
-                }
+            CliCommand::LedOn(led) => {
+                info!("CLI: LED {} on requested", led.number());
+                self.set_led(led, true);
+                let _ = response.push_str("LED ");
+                let _ = response.push((led.number() + b'0') as char);
+                let _ = response.push_str(" turned on");
             }
-            CliCommand::LedOff(led_num) => {
-                info!("CLI: LED {} off requested", led_num);
-                let idx = (led_num - 1) as usize;
-                if idx < 4 {
-                    self.led_states[idx] = false;
-                    let _ = response.push_str("LED ");
-                    let _ = response.push((led_num + b'0') as char);
-                    let _ = response.push_str(" turned off");
-
-                    // Actually control the LED hardware
-                    match led_num {
-                        3 => {
-                            if let Some(ref mut led) = self.led3 {
-                                led.set_high(); // LEDs are active low
-                            }
-                        }
-                        4 => {
-                            if let Some(ref mut led) = self.led4 {
-                                led.set_high(); // LEDs are active low
-                            }
-                        }
-                        _ => {}
-                    }
-                }
+            CliCommand::LedOff(led) => {
+                info!("CLI: LED {} off requested", led.number());
+                self.set_led(led, false);
+                let _ = response.push_str("LED ");
+                let _ = response.push((led.number() + b'0') as char);
+                let _ = response.push_str(" turned off");
             }
             CliCommand::Button => {
                 info!("CLI: Button state requested");
-                let _ = response.push_str("Button States:\r\n");
+                let _ = response.push_str(Msg::ButtonStatesHeading.text());
+                let _ = response.push_str("\r\n");
 
                 // Read button states (buttons are active low)
                 if let (Some(ref btn1), Some(ref btn2), Some(ref btn3), Some(ref btn4)) =
@@ -215,32 +363,26 @@ impl<'d> CommandHandler<'d> {
                 // Use SoftDevice temperature reading
                 match self.read_temperature() {
                     Ok(temp_celsius) => {
-                        let _ = response.push_str("Temperature: ");
-                        // Format temperature with one decimal place manually
-                        let temp_int = temp_celsius as i32;
-                        let temp_frac = ((temp_celsius - temp_int as f32) * 10.0) as i32;
-
-                        // Write integer part (handle negative temperatures)
-                        if temp_int < 0 {
-                            let _ = response.push('-');
-                            let _ = write_num(&mut response, (-temp_int) as u64);
-                        } else {
-                            let _ = write_num(&mut response, temp_int as u64);
-                        }
-                        let _ = response.push('.');
-                        let _ = response.push((b'0' + temp_frac.unsigned_abs() as u8) as char);
-                        let _ = response.push_str("°C");
+                        APP_STATE.update_readings(|r| r.temperature = Some(temp_celsius));
+                        let _ = response.push_str(Msg::TemperatureLabel.text());
+                        let _ = write_temperature(&mut response, temp_celsius);
                     }
                     Err(_) => {
-                        let _ = response.push_str("Failed to read temperature sensor");
+                        APP_STATE.update_stats(|s| s.command_errors += 1);
+                        let _ = response.push_str(Msg::TempReadFailed.text());
                     }
                 }
             }
             CliCommand::BtScan(scan_time) => {
-                let scan_duration = scan_time.unwrap_or(10); // Default 10 seconds
+                let scan_duration = scan_time.unwrap_or(APP_STATE.config().scan_time_secs);
                 info!("CLI: BLE scan requested for {} seconds", scan_duration);
                 match self.perform_scan(scan_duration).await {
                     Ok(scan_results) => {
+                        APP_STATE.update_stats(|s| s.ble_scans += 1);
+                        self.count_lifetime(Counter::BleScans).await;
+                        APP_STATE
+                            .update_readings(|r| r.scan_devices = Some(scan_results.len() as u16));
+                        // Left uncolored: with 10 devices the listing fills the response
                         let _ = response.push_str("BLE scan completed (");
                         let _ = write_num(&mut response, scan_duration as u64);
                         let _ = response.push_str("s) - found ");
@@ -249,12 +391,18 @@ impl<'d> CommandHandler<'d> {
 
                         // Display all devices that fit in the buffer
                         let mut displayed_count = 0;
-                        for addr in scan_results.iter() {
+                        for (index, addr) in scan_results.iter().enumerate() {
                             // Calculate the exact space needed for this address line: "  aa:bb:cc:dd:ee:ff\r\n" = 21 chars
                             let line_length = 21;
+                            // Keep room for the "... and N more" trailer unless this is the last line
+                            let trailer_length = if index + 1 < scan_results.len() {
+                                SCAN_TRAILER_LEN
+                            } else {
+                                0
+                            };
 
                             // Check if this line would fit
-                            if response.len() + line_length > response.capacity() {
+                            if response.len() + line_length + trailer_length > response.capacity() {
                                 break;
                             }
 
@@ -278,21 +426,663 @@ impl<'d> CommandHandler<'d> {
                         }
                     }
                     Err(_) => {
-                        let _ = response.push_str("BLE scan failed");
+                        APP_STATE.update_stats(|s| s.command_errors += 1);
+                        let _ = response.push_str(Msg::ScanFailed.text());
                     }
                 }
             }
+            CliCommand::BtScanCfg(setting) => {
+                match setting {
+                    ScanCfgSetting::Show => {}
+                    ScanCfgSetting::Set(timing) => {
+                        info!(
+                            "CLI: Scan timing set to {}/{} ms",
+                            timing.interval_ms, timing.window_ms
+                        );
+                        APP_STATE.update_config(|c| c.scan_timing = Some(timing));
+                    }
+                    ScanCfgSetting::Default => {
+                        info!("CLI: Scan timing reset to default");
+                        APP_STATE.update_config(|c| c.scan_timing = None);
+                    }
+                }
+                let _ = response.push_str(Msg::ScanTimingLabel.text());
+                match APP_STATE.config().scan_timing {
+                    Some(timing) => {
+                        let _ = response.push_str("interval ");
+                        let _ = write_num(&mut response, timing.interval_ms as u64);
+                        let _ = response.push_str(" ms, window ");
+                        let _ = write_num(&mut response, timing.window_ms as u64);
+                        let _ = response.push_str(" ms (");
+                        let _ = write_percent(
+                            &mut response,
+                            timing.window_ms as u64,
+                            timing.interval_ms as u64,
+                        );
+                        let _ = response.push_str(" duty)");
+                        if timing.window_ms == timing.interval_ms {
+                            let _ = response.push_str("\r\n");
+                            let _ = output::push_colored(
+                                &mut response,
+                                "Warning: continuous scanning starves other radio/CPU work",
+                                Color::Yellow,
+                            );
+                        }
+                    }
+                    None => {
+                        let _ = response.push_str(Msg::ScanTimingDefault.text());
+                    }
+                }
+            }
+            CliCommand::BtName(name) => {
+                if let Some(name) = name {
+                    info!("CLI: Device name set to {}", name.as_str());
+                    if let Some(softdevice) = self.softdevice {
+                        if ble::set_device_name(softdevice, &name).is_err() {
+                            APP_STATE.update_stats(|s| s.command_errors += 1);
+                            let _ = response.push_str(Msg::NameSetFailed.text());
+                            return Ok(response);
+                        }
+                    }
+                    APP_STATE.update_config(|c| c.device_name = name);
+                    self.persist_config(&mut response).await;
+                }
+                let _ = response.push_str(Msg::DeviceNameLabel.text());
+                let config = APP_STATE.config();
+                if config.device_name.is_empty() {
+                    let _ = response.push_str("(built-in)");
+                } else {
+                    let _ = response.push_str(&config.device_name);
+                }
+            }
+            CliCommand::BtAddr(setting) => {
+                match setting {
+                    AddrSetting::Show => {}
+                    AddrSetting::Set(addr) => {
+                        info!("CLI: Static address set");
+                        if let Some(softdevice) = self.softdevice {
+                            ble::set_static_address(softdevice, addr);
+                        }
+                        APP_STATE.update_config(|c| c.ble_addr = Some(addr));
+                        self.persist_config(&mut response).await;
+                    }
+                    AddrSetting::Factory => {
+                        info!("CLI: Factory address restored");
+                        APP_STATE.update_config(|c| c.ble_addr = None);
+                        self.persist_config(&mut response).await;
+                        let _ = response.push_str(Msg::FactoryAddrAfterReset.text());
+                        let _ = response.push_str("\r\n");
+                    }
+                }
+                let _ = response.push_str(Msg::DeviceAddressLabel.text());
+                match self.softdevice {
+                    Some(softdevice) => {
+                        let addr = ble::address(softdevice);
+                        for (i, byte) in addr.iter().rev().enumerate() {
+                            if i > 0 {
+                                let _ = response.push(':');
+                            }
+                            let _ = write_hex_byte(&mut response, *byte);
+                        }
+                        let _ = response.push_str(if APP_STATE.config().ble_addr.is_some() {
+                            " (static)"
+                        } else {
+                            " (factory)"
+                        });
+                    }
+                    None => {
+                        let _ = response.push_str("n/a");
+                    }
+                }
+            }
+            CliCommand::ShowAll => {
+                info!("CLI: Show all requested");
+                let config = APP_STATE.config();
+                let stats = APP_STATE.stats();
+                let readings = APP_STATE.readings();
+
+                let _ = output::push_heading(&mut response, "Config:");
+                let _ = output::push_label(&mut response, "Scan time");
+                let _ = write_num(&mut response, config.scan_time_secs as u64);
+                let _ = response.push_str("s\r\n");
+                let _ = output::push_label(&mut response, "Color");
+                let _ = output::push_on_off(&mut response, config.color);
+                let _ = response.push_str("\r\n");
+
+                let _ = output::push_heading(&mut response, "Statistics:");
+                let _ = output::push_label(&mut response, "Commands");
+                let _ = write_num(&mut response, stats.commands_executed as u64);
+                let succeeded = stats
+                    .commands_executed
+                    .saturating_sub(stats.command_errors + stats.unknown_commands);
+                let _ = response.push_str(" (");
+                let _ = write_percent(
+                    &mut response,
+                    succeeded as u64,
+                    stats.commands_executed as u64,
+                );
+                let _ = response.push_str(" ok)\r\n");
+                let _ = output::push_label(&mut response, "Errors");
+                let _ = write_num(&mut response, stats.command_errors as u64);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Unknown");
+                let _ = write_num(&mut response, stats.unknown_commands as u64);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "BLE scans");
+                let _ = write_num(&mut response, stats.ble_scans as u64);
+                let _ = response.push_str("\r\n");
+
+                let _ = output::push_heading(&mut response, "Readings:");
+                let _ = output::push_label(&mut response, "Temperature");
+                match readings.temperature {
+                    Some(temp_celsius) => {
+                        let _ = write_temperature(&mut response, temp_celsius);
+                    }
+                    None => {
+                        let _ = response.push_str("n/a");
+                    }
+                }
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Last scan");
+                match readings.scan_devices {
+                    Some(count) => {
+                        let _ = write_num(&mut response, count as u64);
+                        let _ = response.push_str(" devices");
+                    }
+                    None => {
+                        let _ = response.push_str("n/a");
+                    }
+                }
+            }
+            CliCommand::Color(setting) => {
+                if let Some(enabled) = setting {
+                    info!("CLI: Color output set to {}", enabled);
+                    APP_STATE.update_config(|c| c.color = enabled);
+                    self.persist_config(&mut response).await;
+                }
+                let _ = response.push_str(Msg::ColorOutputLabel.text());
+                let _ = output::push_on_off(&mut response, APP_STATE.config().color);
+            }
+            CliCommand::Prompt(setting) => {
+                if let Some(compact) = setting {
+                    info!("CLI: Compact prompt set to {}", compact);
+                    APP_STATE.update_config(|c| c.compact_prompt = compact);
+                    self.persist_config(&mut response).await;
+                }
+                let _ = response.push_str(Msg::PromptLabel.text());
+                if APP_STATE.config().compact_prompt {
+                    let _ = response.push_str("compact");
+                } else {
+                    let _ = response.push_str("full");
+                }
+            }
+            CliCommand::SwUartRx(pin, baud, secs, view) => {
+                info!(
+                    "CLI: swuart_rx on pin {} at {} baud for {}s",
+                    pin, baud, secs
+                );
+                if board::is_reserved_pin(pin) {
+                    APP_STATE.update_stats(|s| s.command_errors += 1);
+                    let _ = response.push_str(Msg::PinInUse.text());
+                    return Ok(response);
+                }
+
+                // SAFETY: pins used by the board are rejected above, and the
+                // pin is released again when `input` is dropped
+                let mut input = Input::new(unsafe { AnyPin::steal(pin) }, Pull::Up);
+                let mut received = heapless::Vec::<u8, SWUART_CAPTURE_SIZE>::new();
+                let stats = swuart::receive(
+                    &mut input,
+                    baud,
+                    Duration::from_secs(secs as u64),
+                    &mut received,
+                )
+                .await;
+                info!("CLI: swuart_rx finished: {}", stats);
+
+                let _ = output::push_colored(&mut response, "swuart_rx", Color::Cyan);
+                let _ = response.push_str(": ");
+                let _ = write_num(&mut response, stats.bytes as u64);
+                let _ = response.push_str(" bytes, ");
+                let _ = write_num(&mut response, stats.noise_errors as u64);
+                let _ = response.push_str(" noise, ");
+                let _ = write_num(&mut response, stats.framing_errors as u64);
+                let _ = response.push_str(" framing errors");
+                match view {
+                    CaptureView::Hex => {
+                        for (i, byte) in received.iter().enumerate() {
+                            let _ = response.push_str(if i % 16 == 0 { "\r\n  " } else { " " });
+                            let _ = write_hex_byte(&mut response, *byte);
+                        }
+                    }
+                    CaptureView::Text if !received.is_empty() => {
+                        let _ = response.push_str("\r\n  ");
+                        let _ = write_escaped(&mut response, &received);
+                    }
+                    CaptureView::Text => {}
+                }
+                if stats.overflow > 0 {
+                    let _ = response.push_str("\r\n  ... and ");
+                    let _ = write_num(&mut response, stats.overflow as u64);
+                    let _ = response.push_str(" more");
+                }
+            }
+            CliCommand::Pinspect(pin) => {
+                let state = pinspect::inspect(pin);
+                info!("CLI: pinspect {}: {}", pin, state);
+
+                let _ = response.push_str(if pin < 32 { "P0." } else { "P1." });
+                if pin % 32 < 10 {
+                    let _ = response.push('0');
+                }
+                let _ = write_num(&mut response, (pin % 32) as u64);
+                let _ = response.push_str(" PIN_CNF 0x");
+                for byte in state.cnf.0.to_be_bytes() {
+                    let _ = write_hex_byte(&mut response, byte);
+                }
+                if board::is_reserved_pin(pin) {
+                    let _ = response.push_str(" (board pin)");
+                }
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Direction");
+                let _ = response.push_str(if state.cnf.is_output() {
+                    "output"
+                } else {
+                    "input"
+                });
+                if !state.cnf.input_connected() {
+                    let _ = response.push_str(", input disconnected");
+                }
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Pull");
+                let _ = response.push_str(state.cnf.pull());
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Drive");
+                let _ = response.push_str(state.cnf.drive());
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Sense");
+                let _ = response.push_str(state.cnf.sense());
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "IN/OUT");
+                let _ = response.push(if state.input { '1' } else { '0' });
+                let _ = response.push('/');
+                let _ = response.push(if state.output { '1' } else { '0' });
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "GPIOTE");
+                if state.gpiote.is_empty() {
+                    let _ = response.push_str("none");
+                }
+                for (i, channel) in state.gpiote.iter().enumerate() {
+                    if i > 0 {
+                        let _ = response.push_str(", ");
+                    }
+                    let _ = response.push_str("ch");
+                    let _ = write_num(&mut response, channel.index as u64);
+                    let _ = response.push(' ');
+                    let _ = response.push_str(channel.mode());
+                    let _ = response.push(' ');
+                    let _ = response.push_str(channel.polarity());
+                    if channel.mode() == "task" {
+                        let _ = response.push_str(if channel.outinit_high() {
+                            " init high"
+                        } else {
+                            " init low"
+                        });
+                    }
+                }
+            }
+            CliCommand::SessionLog(setting) => {
+                match setting {
+                    SessionLogSetting::Show => {}
+                    SessionLogSetting::On | SessionLogSetting::Off => {
+                        let on = setting == SessionLogSetting::On;
+                        if on && self.storage.is_none() {
+                            let _ = response.push_str(Msg::SessionLogNeedsStorage.text());
+                            return Ok(response);
+                        }
+                        info!("CLI: Session log {}", if on { "on" } else { "off" });
+                        APP_STATE.update_config(|c| c.session_log = on);
+                        self.persist_config(&mut response).await;
+                    }
+                    SessionLogSetting::Clear => {
+                        info!("CLI: Session log cleared");
+                        let cleared = match self.storage.as_mut() {
+                            Some(storage) => storage.clear_log().await.is_ok(),
+                            None => false,
+                        };
+                        if !cleared {
+                            APP_STATE.update_stats(|s| s.command_errors += 1);
+                            let _ = response.push_str(Msg::SessionLogEraseFailed.text());
+                            let _ = response.push_str("\r\n");
+                        }
+                    }
+                }
+                let _ = response.push_str(Msg::SessionLogLabel.text());
+                let _ = output::push_on_off(&mut response, APP_STATE.config().session_log);
+                let _ = response.push_str(", ");
+                let _ = write_num(&mut response, storage::session_log().count() as u64);
+                let _ = response.push_str(" lines stored");
+            }
+            CliCommand::LogPolicy(policy) => {
+                if let Some(policy) = policy {
+                    info!("CLI: Log policy set to {}", policy);
+                    APP_STATE.update_config(|c| c.log_policy = policy);
+                    self.persist_config(&mut response).await;
+                }
+                let policy = APP_STATE.config().log_policy;
+                let _ = output::push_label(&mut response, "When full");
+                let _ = response.push_str(match policy.mode {
+                    LogMode::Overwrite => "overwrite oldest",
+                    LogMode::StopWhenFull => "stop logging",
+                });
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Max entries");
+                match policy.max_entries {
+                    0 => {
+                        let _ = response.push_str("as many as fit");
+                    }
+                    max => {
+                        let _ = write_num(&mut response, max as u64);
+                    }
+                }
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Stored");
+                let _ = write_num(&mut response, storage::session_log().count() as u64);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Dropped");
+                let dropped = storage::counters()[Counter::LogDiscarded.index()];
+                let _ = write_num(&mut response, dropped as u64);
+                let _ = response.push_str(" (lifetime)");
+            }
+            CliCommand::Meta(setting) => {
+                let field = match setting {
+                    MetaSetting::Show => None,
+                    MetaSetting::Set(field, text) => {
+                        info!("CLI: Metadata {} set", meta_name(field));
+                        APP_STATE.update_config(|c| *meta_field(c, field) = text);
+                        Some(field)
+                    }
+                    MetaSetting::Clear(field) => {
+                        info!("CLI: Metadata {} cleared", meta_name(field));
+                        APP_STATE.update_config(|c| meta_field(c, field).clear());
+                        Some(field)
+                    }
+                };
+                if field.is_some() {
+                    self.persist_config(&mut response).await;
+                }
+
+                // After a change only that field is shown; all three at full
+                // length only just fit in one response
+                let mut config = APP_STATE.config();
+                let mut first = true;
+                for shown in [MetaField::Site, MetaField::Serial, MetaField::Note] {
+                    if field.is_some_and(|f| f != shown) {
+                        continue;
+                    }
+                    if !first {
+                        let _ = response.push_str("\r\n");
+                    }
+                    first = false;
+                    let _ = output::push_label(&mut response, meta_name(shown));
+                    let value = meta_field(&mut config, shown).as_str();
+                    let _ = response.push_str(if value.is_empty() { "(not set)" } else { value });
+                }
+            }
+            CliCommand::Lifetime => {
+                info!("CLI: Lifetime counters requested");
+                let totals = storage::counters();
+                let _ = output::push_heading(&mut response, "Lifetime:");
+                for counter in Counter::ALL {
+                    let total = totals[counter.index()] + self.pending_counts[counter.index()];
+                    let _ = output::push_label(&mut response, counter.label());
+                    let _ = write_num(&mut response, total as u64);
+                    let _ = response.push_str("\r\n");
+                }
+                if self.storage.is_none() {
+                    let _ = response.push_str(Msg::NotCountingSafeMode.text());
+                }
+            }
+            CliCommand::MemBudget => {
+                info!("CLI: RAM budget requested");
+                let budget = mem_budget::ram_budget();
+                let _ = output::push_heading(&mut response, "RAM Budget:");
+                let rows = [
+                    ("SoftDevice", budget.softdevice),
+                    ("Statics", budget.statics),
+                    ("Stack used", budget.stack_used),
+                    ("Headroom", budget.headroom),
+                    ("App state", core::mem::size_of::<AppState>() as u32),
+                    ("CLI history", (MAX_HISTORY_SIZE * CLI_BUFFER_SIZE) as u32),
+                    ("Response", RESPONSE_SIZE as u32),
+                ];
+                for (label, bytes) in rows {
+                    let _ = output::push_label(&mut response, label);
+                    let _ = write_num(&mut response, bytes as u64);
+                    let _ = response.push_str(" B\r\n");
+                }
+                if budget.is_tight() {
+                    warn!("RAM headroom low: {} bytes", budget.headroom);
+                    let _ = output::push_colored(&mut response, "Headroom LOW", Color::Red);
+                }
+            }
+            CliCommand::StorageFsck => {
+                info!("CLI: Storage check requested");
+                let result = match self.storage.as_mut() {
+                    Some(storage) => storage.fsck().await,
+                    None => {
+                        let _ = response.push_str(Msg::StorageUnavailableSafeMode.text());
+                        return Ok(response);
+                    }
+                };
+                let report = match result {
+                    Ok(report) => report,
+                    Err(_) => {
+                        APP_STATE.update_stats(|s| s.command_errors += 1);
+                        let _ = response.push_str(Msg::StorageCheckFailed.text());
+                        return Ok(response);
+                    }
+                };
+                let _ = output::push_label(&mut response, "Config");
+                let _ = write_num(&mut response, report.config_slots_ok as u64);
+                let _ = response.push_str("/2 slots ok");
+                if report.config_slots_erased > 0 {
+                    let _ = response.push_str(", ");
+                    let _ = write_num(&mut response, report.config_slots_erased as u64);
+                    let _ = response.push_str(" damaged slot(s) erased");
+                }
+                if report.config_sealed {
+                    let _ = response.push_str(", record sealed");
+                }
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Session log");
+                let _ = write_num(&mut response, report.log_records as u64);
+                let _ = response.push_str(" records");
+                if report.log_corrupt > 0 {
+                    let _ = response.push_str(", ");
+                    let _ = write_num(&mut response, report.log_corrupt as u64);
+                    let _ = response.push_str(" corrupt (skipped)");
+                }
+                let _ = response.push_str("\r\n");
+                if report.found_damage() {
+                    let _ = output::push_colored(&mut response, "Repairs made", Color::Yellow);
+                } else {
+                    let _ = output::push_colored(&mut response, "No damage found", Color::Green);
+                }
+            }
+            CliCommand::Supply(threshold) => {
+                if let Some(threshold_mv) = threshold {
+                    info!("CLI: Low-supply threshold set to {} mV", threshold_mv);
+                    APP_STATE.update_config(|c| c.low_supply_mv = threshold_mv);
+                    self.persist_config(&mut response).await;
+                }
+                let _ = output::push_label(&mut response, "VDD");
+                self.push_supply(&mut response);
+                let _ = response.push_str("\r\n");
+                let _ = output::push_label(&mut response, "Low below");
+                match APP_STATE.config().low_supply_mv {
+                    0 => {
+                        let _ = response.push_str("off");
+                    }
+                    threshold_mv => {
+                        let _ = write_num(&mut response, threshold_mv as u64);
+                        let _ = response.push_str(" mV");
+                    }
+                }
+            }
+            CliCommand::Watch(_, _)
+            | CliCommand::BenchUart(_, _)
+            | CliCommand::EchoHex(_)
+            | CliCommand::Loopback(_)
+            | CliCommand::SessionDump => {
+                // Driven by the caller, which owns the terminal
+            }
+            CliCommand::Time(_) => {
+                // Handled by execute_command
+            }
             CliCommand::Unknown(cmd) => {
                 info!("CLI: Unknown command: {}", cmd.as_str());
-                let _ = response.push_str("Unknown command: ");
+                let _ = response.push_str(Msg::UnknownCommand.text());
                 let _ = response.push_str(&cmd);
-                let _ = response.push_str(". Type 'help' for available commands.");
+                let _ = response.push_str(Msg::TypeHelp.text());
+            }
+            CliCommand::InvalidArgs(error) => {
+                info!("CLI: {}: invalid arguments", error.command);
+                push_arg_error(&mut response, &error);
             }
         }
 
         Ok(response)
     }
 
+    /// Append a command line and the text written back to the session log
+    ///
+    /// Does nothing unless `session_log on` is set. Output produced directly
+    /// by the session (help, watch, benchmarks) is not logged.
+    pub async fn log_session(&mut self, input: &str, output: &str) {
+        let config = APP_STATE.config();
+        if !config.session_log {
+            return;
+        }
+        let policy = config.log_policy;
+        if let Some(storage) = self.storage.as_mut() {
+            let logged = storage
+                .append_log(LogDirection::Input, input, policy)
+                .await
+                .is_ok()
+                && (output.is_empty()
+                    || storage
+                        .append_log(LogDirection::Output, output, policy)
+                        .await
+                        .is_ok());
+            if !logged {
+                warn!("CLI: Session log write failed");
+            }
+        }
+    }
+
+    /// Add a typed command to the session and lifetime statistics
+    async fn count_command(&mut self, command: &CliCommand) {
+        match command {
+            CliCommand::Empty => return,
+            CliCommand::Unknown(_) => APP_STATE.update_stats(|s| {
+                s.commands_executed += 1;
+                s.unknown_commands += 1;
+            }),
+            CliCommand::InvalidArgs(_) => APP_STATE.update_stats(|s| {
+                s.commands_executed += 1;
+                s.command_errors += 1;
+            }),
+            _ => APP_STATE.update_stats(|s| s.commands_executed += 1),
+        }
+        self.count_lifetime(Counter::Commands).await;
+    }
+
+    /// Bump a lifetime counter in RAM, writing the counts out once enough
+    /// have built up or enough time has passed, to spare the flash
+    async fn count_lifetime(&mut self, counter: Counter) {
+        if self.storage.is_none() {
+            return;
+        }
+        self.pending_counts[counter.index()] += 1;
+        let pending: u32 = self.pending_counts.iter().sum();
+        if pending >= COUNTER_FLUSH_COUNT
+            || Instant::now() - self.last_counter_flush >= COUNTER_FLUSH_INTERVAL
+        {
+            self.flush_lifetime().await;
+        }
+    }
+
+    /// Write the lifetime counts held in RAM to flash
+    ///
+    /// Called before a graceful reset; counts that fail to save stay pending.
+    pub async fn flush_lifetime(&mut self) {
+        self.last_counter_flush = Instant::now();
+        let Some(storage) = self.storage.as_mut() else {
+            return;
+        };
+        for counter in Counter::ALL {
+            let pending = self.pending_counts[counter.index()];
+            if pending == 0 {
+                continue;
+            }
+            match storage.add_to_counter(counter, pending).await {
+                Ok(_) => self.pending_counts[counter.index()] = 0,
+                Err(_) => warn!("CLI: Lifetime counter update failed"),
+            }
+        }
+    }
+
+    /// Append the last VDD sample and the low-supply flag
+    fn push_supply(&self, response: &mut String<RESPONSE_SIZE>) {
+        let readings = APP_STATE.readings();
+        match readings.vdd_mv {
+            Some(vdd_mv) => {
+                let _ = write_num(response, vdd_mv as u64);
+                let _ = response.push_str(" mV");
+            }
+            None => {
+                let _ = response.push_str("not sampled");
+            }
+        }
+        if readings.low_supply {
+            let _ = response.push(' ');
+            let _ = output::push_colored(response, "LOW", Color::Red);
+        }
+    }
+
+    /// Save the current config to flash, noting any failure in `response`
+    async fn persist_config(&mut self, response: &mut String<RESPONSE_SIZE>) {
+        let saved = match self.storage.as_mut() {
+            Some(storage) => storage.save_config(&APP_STATE.config()).await.is_ok(),
+            None => false,
+        };
+        if !saved {
+            APP_STATE.update_stats(|s| s.command_errors += 1);
+            let _ = response.push_str(Msg::NotSaved.text());
+            let _ = response.push_str("\r\n");
+        }
+    }
+
+    fn set_led(&mut self, led: LedId, on: bool) {
+        self.led_states[led.index()] = on;
+
+        // Actually control the LED hardware
+        let output = match led.number() {
+            3 => self.led3.as_mut(),
+            4 => self.led4.as_mut(),
+            _ => None,
+        };
+        if let Some(output) = output {
+            // LEDs are active low
+            if on {
+                output.set_low();
+            } else {
+                output.set_high();
+            }
+        }
+    }
+
     fn read_temperature(&self) -> Result<f32, CliError> {
         // Read temperature using SoftDevice
         if let Some(softdevice) = self.softdevice {
@@ -309,17 +1099,25 @@ impl<'d> CommandHandler<'d> {
         }
     }
 
-    async fn perform_scan(&self, scan_time: u16) -> Result<heapless::Vec<[u8; 6], 10>, CliError> {
+    async fn perform_scan(
+        &self,
+        scan_time: u16,
+    ) -> Result<heapless::Vec<[u8; 6], MAX_SCAN_RESULTS>, CliError> {
         if let Some(softdevice) = self.softdevice {
             info!("Starting BLE scan for {} seconds", scan_time);
             let start_time = embassy_time::Instant::now();
 
-            let config = central::ScanConfig {
+            let mut config = central::ScanConfig {
                 timeout: scan_time * 100, // Convert seconds to 10ms units (1 sec = 100 * 10ms)
                 ..Default::default()
             };
+            if let Some(timing) = APP_STATE.config().scan_timing {
+                // Interval and window are in 0.625ms units
+                config.interval = timing.interval_ms as u32 * 8 / 5;
+                config.window = timing.window_ms as u32 * 8 / 5;
+            }
 
-            let mut discovered_devices = heapless::Vec::<[u8; 6], 10>::new();
+            let mut discovered_devices = heapless::Vec::<[u8; 6], MAX_SCAN_RESULTS>::new();
 
             let result = central::scan(softdevice, &config, |params| {
                 let addr = params.peer_addr.addr;
@@ -333,7 +1131,10 @@ impl<'d> CommandHandler<'d> {
                         );
                         None::<()> // Continue scanning
                     } else {
-                        info!("Device buffer full (10 devices), stopping scan...");
+                        info!(
+                            "Device buffer full ({} devices), stopping scan...",
+                            MAX_SCAN_RESULTS
+                        );
                         Some(()) // Stop scanning - buffer is full
                     }
                 } else {
@@ -374,33 +1175,67 @@ impl<'d> CommandHandler<'d> {
     }
 }
 
-// Helper function to write numbers to string without using std::fmt
-fn write_num(s: &mut String<256>, mut num: u64) -> Result<(), ()> {
-    if num == 0 {
-        return s.push('0').map_err(|_| ());
-    }
-
-    let mut digits = heapless::Vec::<u8, 20>::new();
-    while num > 0 {
-        let _ = digits.push((num % 10) as u8);
-        num /= 10;
+/// Label used for a metadata field in responses and logs
+fn meta_name(field: MetaField) -> &'static str {
+    match field {
+        MetaField::Site => "Site",
+        MetaField::Serial => "Serial",
+        MetaField::Note => "Note",
     }
+}
 
-    for &digit in digits.iter().rev() {
-        s.push((b'0' + digit) as char).map_err(|_| ())?;
+/// The config string holding `field`
+fn meta_field(config: &mut Config, field: MetaField) -> &mut String<MAX_META_LEN> {
+    match field {
+        MetaField::Site => &mut config.site,
+        MetaField::Serial => &mut config.meter_serial,
+        MetaField::Note => &mut config.note,
     }
-
-    Ok(())
 }
 
-// Helper function to write hex byte to string
-fn write_hex_byte(s: &mut String<256>, byte: u8) -> Result<(), ()> {
-    let hex_chars = b"0123456789abcdef";
-    let high = (byte >> 4) & 0x0f;
-    let low = byte & 0x0f;
-
-    s.push(hex_chars[high as usize] as char).map_err(|_| ())?;
-    s.push(hex_chars[low as usize] as char).map_err(|_| ())?;
-
-    Ok(())
+/// `<command>: '<arg>' <problem>` followed by the command's usage
+fn push_arg_error(response: &mut String<RESPONSE_SIZE>, error: &ArgError) {
+    let _ = response.push_str(error.command);
+    let _ = response.push_str(": ");
+    if let Some(arg) = &error.arg {
+        let _ = response.push('\'');
+        let _ = response.push_str(arg);
+        let _ = response.push_str("' ");
+    }
+    match error.problem {
+        ArgProblem::Missing(name) => {
+            let _ = response.push_str(Msg::ArgMissing.text());
+            let _ = response.push_str(name);
+        }
+        ArgProblem::NotANumber => {
+            let _ = response.push_str(Msg::ArgNotANumber.text());
+        }
+        ArgProblem::OutOfRange(min, max) => {
+            let _ = response.push_str(Msg::ArgOutOfRange.text());
+            let _ = write_num(response, min as u64);
+            let _ = response.push('-');
+            let _ = write_num(response, max as u64);
+        }
+        ArgProblem::NotAChoice => {
+            let _ = response.push_str(Msg::ArgNotAChoice.text());
+        }
+        ArgProblem::TooLong(max) => {
+            let _ = response.push_str(Msg::ArgTooLong.text());
+            let _ = write_num(response, max as u64);
+            let _ = response.push(')');
+        }
+        ArgProblem::Invalid(reason) => {
+            let _ = response.push_str(reason);
+        }
+        ArgProblem::Unexpected => {
+            let _ = response.push_str(Msg::ArgUnexpected.text());
+        }
+    }
+    let _ = response.push_str("\r\n");
+    let _ = response.push_str(Msg::Usage.text());
+    let _ = response.push_str(error.command);
+    if !error.usage().is_empty() {
+        let _ = response.push(' ');
+        let _ = response.push_str(error.usage());
+    }
 }
@@ -1,31 +1,173 @@
+pub mod args;
+#[cfg(feature = "cli")]
 pub mod commands;
+pub mod output;
 pub mod parser;
+pub mod registry;
+#[cfg(feature = "cli")]
+pub mod session;
+pub mod strings;
+#[cfg(feature = "cli")]
 pub mod terminal;
+#[cfg(feature = "cli")]
+pub mod transport;
 
+#[cfg(feature = "cli")]
 pub use commands::CommandHandler;
 pub use parser::CommandParser;
+#[cfg(feature = "cli")]
 pub use terminal::Terminal;
+#[cfg(feature = "cli")]
+pub use transport::{Transport, UsbSerial};
+
+use crate::led::LedId;
+use crate::state::{LogPolicy, ScanTiming, MAX_DEVICE_NAME_LEN, MAX_META_LEN};
+use args::ArgError;
 
 // CLI-related types and constants
+//
+// Every fixed-capacity buffer in the CLI is sized here. All of them live in
+// static RAM or on the task stack, so boards with RAM to spare can raise them
+// without touching the modules that use them.
+
+/// Longest input line, including room for tab completion
 pub const CLI_BUFFER_SIZE: usize = 128;
+/// Lines kept for ↑/↓ history (each costs CLI_BUFFER_SIZE bytes)
 pub const MAX_HISTORY_SIZE: usize = 10;
+/// Largest command response; `show all` and a full `bt_scan` listing, each
+/// with colors and a `time` line added, stay well inside it
+pub const RESPONSE_SIZE: usize = 512;
+/// Error and unknown-command messages, which must stay short enough to fit
+pub const MESSAGE_SIZE: usize = 32;
+/// Free-text arguments (`echo` text, the command line given to `time` or `watch`)
+pub const ARG_SIZE: usize = 64;
+/// Whitespace-separated words considered when parsing `echo` and completing
+pub const MAX_ARGS: usize = 8;
+/// Matches listed by tab completion, enough for every command so an empty
+/// prefix lists them all
+pub const MAX_COMPLETIONS: usize = registry::COMMANDS.len();
+/// Unique devices kept by `bt_scan`; more are counted but not listed
+pub const MAX_SCAN_RESULTS: usize = 10;
+/// Raw bytes `echo_hex` sends, about what fits on one input line as hex
+pub const ECHO_HEX_SIZE: usize = 48;
+/// Largest `loopback` transfer in bytes
+pub const MAX_LOOPBACK_BYTES: u16 = 4096;
+/// Bytes per transport write when the terminal prints text (on the stack)
+pub const TX_CHUNK_SIZE: usize = 64;
+/// Bytes shown by `swuart_rx`, about what fits in one response as hex
+pub const SWUART_CAPTURE_SIZE: usize = 48;
 
 #[derive(Debug, Clone)]
 pub enum CliCommand {
     Help,
     Version,
+    BuildInfo,
     Status,
     Uptime,
+    Date(Option<u32>), // Unix time to set, None shows the wall clock
     Clear,
-    Reset,
-    Echo(heapless::String<64>),
-    LedOn(u8),
-    LedOff(u8),
+    Reset(ResetMode),
+    Echo(heapless::String<ARG_SIZE>),
+    LedOn(LedId),
+    LedOff(LedId),
     Button,
     Temp,
     BtScan(Option<u16>), // Optional scan time in seconds
+    ShowAll,
+    Color(Option<bool>),                       // None shows the current setting
+    Prompt(Option<bool>),                      // Compact or not, None shows the current setting
+    Watch(heapless::String<ARG_SIZE>, u16),    // Command line to repeat and interval in seconds
+    Time(heapless::String<ARG_SIZE>),          // Command line to execute and time
+    BenchUart(u32, bool),                      // Byte count and whether the host echoes it back
+    EchoHex(heapless::Vec<u8, ECHO_HEX_SIZE>), // Raw bytes to send back
+    Loopback(u16),                             // Raw bytes to receive and echo
+    SwUartRx(u8, u32, u16, CaptureView),       // Pin (port * 32 + pin), baud rate, seconds and view
+    Pinspect(u8),                              // Pin (port * 32 + pin)
+    BtName(Option<heapless::String<MAX_DEVICE_NAME_LEN>>), // None shows the current name
+    BtAddr(AddrSetting),
+    BtScanCfg(ScanCfgSetting),
+    SessionLog(SessionLogSetting),
+    SessionDump,
+    LogPolicy(Option<LogPolicy>), // None shows the current policy
+    Meta(MetaSetting),
+    StorageFsck,
+    Lifetime,
+    MemBudget,
+    Supply(Option<u16>), // New low-supply threshold in mV (0 = off), None shows the supply
     Empty,
-    Unknown(heapless::String<32>),
+    Unknown(heapless::String<MESSAGE_SIZE>),
+    InvalidArgs(ArgError), // Known command, rejected arguments
+}
+
+/// How `swuart_rx` shows the captured bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureView {
+    /// Every byte as two hex digits
+    Hex,
+    /// Printable ASCII as is, anything else escaped as `\xNN`
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResetMode {
+    /// Flush the response and log before resetting
+    Graceful,
+    /// Reset immediately, skipping the flush
+    Now,
+    /// Reboot into the DFU bootloader
+    Bootloader,
+    /// Reboot into safe mode (no autorun or background tasks)
+    Safe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddrSetting {
+    /// Show the current device address
+    Show,
+    /// Use this static random address (LSB first)
+    Set([u8; 6]),
+    /// Go back to the factory address on the next reset
+    Factory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanCfgSetting {
+    /// Show the current scan interval and window
+    Show,
+    /// Use this interval and window for subsequent scans
+    Set(ScanTiming),
+    /// Go back to the SoftDevice default timing
+    Default,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionLogSetting {
+    /// Show whether logging is on and how much is stored
+    Show,
+    /// Start appending commands and responses to flash
+    On,
+    /// Stop logging, keeping what is stored
+    Off,
+    /// Erase the stored log
+    Clear,
+}
+
+/// Installation metadata fields kept in the config
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetaField {
+    Site,
+    Serial,
+    Note,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetaSetting {
+    /// Show all metadata fields
+    Show,
+    /// Store this text in the field
+    Set(MetaField, heapless::String<MAX_META_LEN>),
+    /// Empty the field
+    Clear(MetaField),
 }
 
 #[derive(Debug)]
@@ -33,5 +175,20 @@ pub enum CliError {
     InvalidCommand,
     InvalidArgument,
     UartError,
+    /// The receiver flagged a line condition on incoming data
+    Line(LineError),
     BufferFull,
 }
+
+/// Receive errors reported by the console UART
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum LineError {
+    /// No valid stop bit, usually a baud rate mismatch or line noise
+    Framing,
+    /// Parity bit did not match
+    Parity,
+    /// A byte arrived before the previous one was read
+    Overrun,
+    /// The line was held low for longer than a frame
+    Break,
+}
@@ -0,0 +1,83 @@
+//! Output formatting helpers
+//!
+//! ANSI colors (when enabled with `color on`) and fixed-width label columns
+//! used by the denser status screens.
+
+use crate::state::APP_STATE;
+use heapless::String;
+
+/// Width of the label column in `label: value` listings
+pub const LABEL_WIDTH: usize = 12;
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Cyan,
+    Bold,
+}
+
+impl Color {
+    fn code(self) -> &'static str {
+        match self {
+            Color::Red => "\x1b[31m",
+            Color::Green => "\x1b[32m",
+            Color::Yellow => "\x1b[33m",
+            Color::Cyan => "\x1b[36m",
+            Color::Bold => "\x1b[1m",
+        }
+    }
+}
+
+pub fn colors_enabled() -> bool {
+    APP_STATE.config().color
+}
+
+/// Append `text`, wrapped in ANSI color codes if colors are enabled
+pub fn push_colored<const N: usize>(s: &mut String<N>, text: &str, color: Color) -> Result<(), ()> {
+    if colors_enabled() {
+        s.push_str(color.code())?;
+        s.push_str(text)?;
+        s.push_str(ANSI_RESET)
+    } else {
+        s.push_str(text)
+    }
+}
+
+/// Append `text` left-aligned in a column of `width` characters
+pub fn push_padded<const N: usize>(s: &mut String<N>, text: &str, width: usize) -> Result<(), ()> {
+    s.push_str(text)?;
+    for _ in text.chars().count()..width {
+        s.push(' ')?;
+    }
+    Ok(())
+}
+
+/// Append an indented `label:` padded to the label column
+pub fn push_label<const N: usize>(s: &mut String<N>, label: &str) -> Result<(), ()> {
+    s.push_str("  ")?;
+    s.push_str(label)?;
+    s.push(':')?;
+    for _ in label.chars().count() + 1..LABEL_WIDTH {
+        s.push(' ')?;
+    }
+    Ok(())
+}
+
+/// Append a section heading followed by a line break
+pub fn push_heading<const N: usize>(s: &mut String<N>, title: &str) -> Result<(), ()> {
+    push_colored(s, title, Color::Bold)?;
+    s.push_str("\r\n")
+}
+
+/// Append "on" in green or "off" in red
+pub fn push_on_off<const N: usize>(s: &mut String<N>, on: bool) -> Result<(), ()> {
+    if on {
+        push_colored(s, "on", Color::Green)
+    } else {
+        push_colored(s, "off", Color::Red)
+    }
+}
@@ -1,4 +1,14 @@
-use super::CliCommand;
+use super::args::{ArgError, ArgProblem, Args};
+use super::registry::{self, CommandId, COMMANDS};
+use super::{
+    AddrSetting, CaptureView, CliCommand, MetaField, MetaSetting, ResetMode, ScanCfgSetting,
+    SessionLogSetting, ARG_SIZE, ECHO_HEX_SIZE, MAX_ARGS, MAX_COMPLETIONS, MAX_LOOPBACK_BYTES,
+};
+use crate::led::LedId;
+use crate::state::{
+    LogMode, LogPolicy, ScanTiming, MAX_LOW_SUPPLY_MV, MAX_SCAN_TIME_SECS, MIN_LOW_SUPPLY_MV,
+};
+use crate::swuart;
 use heapless::String;
 
 pub struct CommandParser;
@@ -9,28 +19,34 @@ impl Default for CommandParser {
     }
 }
 
+/// Read-only commands that may be repeated with `watch`
+const WATCHABLE_COMMANDS: &[&str] = &[
+    "status", "uptime", "date", "button", "temp", "show", "supply",
+];
+
+/// Default `watch` refresh interval in seconds
+const DEFAULT_WATCH_INTERVAL: u16 = 2;
+
+/// Default `swuart_rx` capture time in seconds
+const DEFAULT_SWUART_SECS: u16 = 5;
+
+/// Largest `bench_uart` transfer in bytes
+const MAX_BENCH_BYTES: u32 = 100_000;
+
 impl CommandParser {
     pub fn new() -> Self {
         Self
     }
 
-    pub fn get_available_commands() -> &'static [&'static str] {
-        &[
-            "help", "version", "status", "uptime", "clear", "reset", "echo", "led_on", "led_off",
-            "button", "temp", "bt_scan",
-        ]
-    }
-
-    pub fn autocomplete(partial: &str) -> heapless::Vec<&'static str, 10> {
+    /// Commands whose name starts with `partial`
+    pub fn autocomplete(partial: &str) -> heapless::Vec<&'static str, MAX_COMPLETIONS> {
         let mut matches = heapless::Vec::new();
-        let commands = Self::get_available_commands();
-
-        for &cmd in commands {
-            if cmd.starts_with(partial) {
-                let _ = matches.push(cmd);
+        for spec in COMMANDS {
+            if spec.name.starts_with(partial) {
+                // Sized for the whole registry, so no match is dropped
+                let _ = matches.push(spec.name);
             }
         }
-
         matches
     }
 
@@ -40,95 +56,306 @@ impl CommandParser {
             return CliCommand::Empty;
         }
 
-        let mut parts = trimmed.split_whitespace();
-        let cmd = parts.next().unwrap_or("");
-
-        match cmd {
-            "help" => CliCommand::Help,
-            "version" => CliCommand::Version,
-            "status" => CliCommand::Status,
-            "uptime" => CliCommand::Uptime,
-            "clear" => CliCommand::Clear,
-            "reset" => CliCommand::Reset,
-            "button" => CliCommand::Button,
-            "temp" => CliCommand::Temp,
-            "bt_scan" => {
-                if let Some(arg) = parts.next() {
-                    if let Ok(scan_time) = arg.parse::<u16>() {
-                        if scan_time > 0 && scan_time <= 60 {
-                            CliCommand::BtScan(Some(scan_time))
-                        } else {
-                            let mut msg = String::new();
-                            let _ = msg.push_str("bt_scan: scan time must be 1-60 seconds");
-                            CliCommand::Unknown(msg)
-                        }
-                    } else {
-                        let mut msg = String::new();
-                        let _ = msg.push_str("bt_scan: invalid scan time");
-                        CliCommand::Unknown(msg)
+        let (cmd, rest) = trimmed
+            .split_once(char::is_whitespace)
+            .unwrap_or((trimmed, ""));
+        let Some(spec) = registry::find(cmd) else {
+            let mut unknown_cmd = String::new();
+            let _ = unknown_cmd.push_str(cmd);
+            return CliCommand::Unknown(unknown_cmd);
+        };
+
+        let mut args = Args::new(spec.name, rest);
+        Self::parse_args(spec.id, &mut args).unwrap_or_else(CliCommand::InvalidArgs)
+    }
+
+    fn parse_args(id: CommandId, args: &mut Args) -> Result<CliCommand, ArgError> {
+        let command = match id {
+            CommandId::Help => CliCommand::Help,
+            CommandId::Version => CliCommand::Version,
+            CommandId::BuildInfo => CliCommand::BuildInfo,
+            CommandId::Status => CliCommand::Status,
+            CommandId::Uptime => CliCommand::Uptime,
+            CommandId::Date => CliCommand::Date(args.opt_number(1..=u32::MAX)?),
+            CommandId::Clear => CliCommand::Clear,
+            CommandId::Reset => {
+                let mode = args.opt_choice(&[
+                    ("now", ResetMode::Now),
+                    ("bootloader", ResetMode::Bootloader),
+                    ("safe", ResetMode::Safe),
+                ])?;
+                CliCommand::Reset(mode.unwrap_or(ResetMode::Graceful))
+            }
+            CommandId::Button => CliCommand::Button,
+            CommandId::Temp => CliCommand::Temp,
+            // None uses the default time
+            CommandId::BtScan => CliCommand::BtScan(args.opt_number(1..=MAX_SCAN_TIME_SECS)?),
+            CommandId::BtScanCfg => match args.peek() {
+                None => CliCommand::BtScanCfg(ScanCfgSetting::Show),
+                Some("default") => {
+                    args.next_word();
+                    CliCommand::BtScanCfg(ScanCfgSetting::Default)
+                }
+                Some(_) => {
+                    let interval =
+                        args.number("interval", ScanTiming::MIN_MS..=ScanTiming::MAX_MS)?;
+                    // Ranges above already make this valid
+                    let window = args.number("window", ScanTiming::MIN_MS..=interval)?;
+                    match ScanTiming::new(interval, window) {
+                        Some(timing) => CliCommand::BtScanCfg(ScanCfgSetting::Set(timing)),
+                        None => return Err(args.error(None, ArgProblem::Invalid("bad timing"))),
                     }
-                } else {
-                    CliCommand::BtScan(None) // Default time
                 }
+            },
+            CommandId::BtName => CliCommand::BtName(args.opt_text()?),
+            CommandId::BtAddr => match args.next_word() {
+                None => CliCommand::BtAddr(AddrSetting::Show),
+                Some("factory") => CliCommand::BtAddr(AddrSetting::Factory),
+                Some(arg) => match Self::parse_addr(arg) {
+                    // Static random addresses have the two top bits set
+                    Some(addr) if addr[5] & 0xC0 == 0xC0 => {
+                        CliCommand::BtAddr(AddrSetting::Set(addr))
+                    }
+                    Some(_) => {
+                        return Err(
+                            args.error(Some(arg), ArgProblem::Invalid("is not static random"))
+                        )
+                    }
+                    None => {
+                        return Err(args.error(Some(arg), ArgProblem::Invalid("is not an address")))
+                    }
+                },
+            },
+            CommandId::Show => {
+                args.opt_choice(&[("all", ())])?;
+                CliCommand::ShowAll
             }
-            "echo" => {
-                let args: heapless::Vec<&str, 8> = parts.collect();
-                let mut echo_string = heapless::String::new();
-                for (i, arg) in args.iter().enumerate() {
-                    if i > 0 {
-                        let _ = echo_string.push(' ');
+            CommandId::Color => {
+                CliCommand::Color(args.opt_choice(&[("on", true), ("off", false)])?)
+            }
+            CommandId::Prompt => {
+                CliCommand::Prompt(args.opt_choice(&[("full", false), ("compact", true)])?)
+            }
+            CommandId::Watch => {
+                // The interval goes before the command, or last as in `watch status 5`
+                let leading = match args.peek() {
+                    Some(word) if word.starts_with(|c: char| c.is_ascii_digit()) => {
+                        args.opt_number(1..=60u16)?
+                    }
+                    _ => None,
+                };
+                let mut line = args.take_remainder();
+                let mut interval = leading;
+                if leading.is_none() {
+                    if let Some((head, last)) = line.rsplit_once(char::is_whitespace) {
+                        if last.starts_with(|c: char| c.is_ascii_digit()) {
+                            interval = Args::new(args.command(), last).opt_number(1..=60u16)?;
+                            line = head.trim_end();
+                        }
                     }
-                    let _ = echo_string.push_str(arg);
                 }
-                CliCommand::Echo(echo_string)
+
+                let (target, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+                if target.is_empty() {
+                    return Err(args.error(None, ArgProblem::Missing("cmd")));
+                }
+                let spec = registry::find(target)
+                    .filter(|spec| WATCHABLE_COMMANDS.contains(&spec.name))
+                    .ok_or_else(|| {
+                        args.error(Some(target), ArgProblem::Invalid("is not watchable"))
+                    })?;
+                // The watched command's own arguments, checked now rather than
+                // on every refresh, and only in forms that change nothing
+                let watched = Self::parse_args(spec.id, &mut Args::new(spec.name, rest))?;
+                if !matches!(
+                    watched,
+                    CliCommand::Status
+                        | CliCommand::Uptime
+                        | CliCommand::Date(None)
+                        | CliCommand::Button
+                        | CliCommand::Temp
+                        | CliCommand::ShowAll
+                        | CliCommand::Supply(None)
+                ) {
+                    return Err(args.error(Some(target), ArgProblem::Invalid("is not watchable")));
+                }
+
+                let mut command_line = String::new();
+                if command_line.push_str(line).is_err() {
+                    return Err(args.error(None, ArgProblem::TooLong(ARG_SIZE)));
+                }
+                CliCommand::Watch(command_line, interval.unwrap_or(DEFAULT_WATCH_INTERVAL))
+            }
+            CommandId::Time => CliCommand::Time(args.text("cmd")?),
+            CommandId::BenchUart => {
+                let bytes = args.number("bytes", 1..=MAX_BENCH_BYTES)?;
+                let echoed = args.opt_choice(&[("loop", true)])?;
+                CliCommand::BenchUart(bytes, echoed.unwrap_or(false))
             }
-            "led_on" => {
-                if let Some(arg) = parts.next() {
-                    if let Ok(led_num) = arg.parse::<u8>() {
-                        if led_num == 3 || led_num == 4 {
-                            CliCommand::LedOn(led_num)
-                        } else {
-                            let mut msg = String::new();
-                            let _ = msg.push_str("led_on: LED must be 3 or 4");
-                            CliCommand::Unknown(msg)
+            CommandId::EchoHex => {
+                let hex = args.take_remainder();
+                if hex.is_empty() {
+                    return Err(args.error(None, ArgProblem::Missing("hex")));
+                }
+                let mut bytes = heapless::Vec::new();
+                for word in hex.split_whitespace() {
+                    // from_str_radix alone would take a sign, as in `+f`
+                    if word.len() % 2 != 0 || !word.bytes().all(|b| b.is_ascii_hexdigit()) {
+                        return Err(args.error(Some(word), ArgProblem::Invalid("is not hex")));
+                    }
+                    for pair in word.as_bytes().chunks(2) {
+                        let byte = core::str::from_utf8(pair)
+                            .ok()
+                            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                            .ok_or_else(|| {
+                                args.error(Some(word), ArgProblem::Invalid("is not hex"))
+                            })?;
+                        if bytes.push(byte).is_err() {
+                            return Err(args.error(None, ArgProblem::TooLong(ECHO_HEX_SIZE)));
                         }
-                    } else {
-                        let mut msg = String::new();
-                        let _ = msg.push_str("led_on: Invalid LED number");
-                        CliCommand::Unknown(msg)
                     }
-                } else {
-                    let mut msg = String::new();
-                    let _ = msg.push_str("led_on: Missing LED number");
-                    CliCommand::Unknown(msg)
                 }
+                CliCommand::EchoHex(bytes)
             }
-            "led_off" => {
-                if let Some(arg) = parts.next() {
-                    if let Ok(led_num) = arg.parse::<u8>() {
-                        if led_num == 3 || led_num == 4 {
-                            CliCommand::LedOff(led_num)
-                        } else {
-                            let mut msg = String::new();
-                            let _ = msg.push_str("led_off: LED must be 3 or 4");
-                            CliCommand::Unknown(msg)
-                        }
-                    } else {
-                        let mut msg = String::new();
-                        let _ = msg.push_str("led_off: Invalid LED number");
-                        CliCommand::Unknown(msg)
+            CommandId::Loopback => {
+                CliCommand::Loopback(args.number("count", 1..=MAX_LOOPBACK_BYTES)?)
+            }
+            CommandId::SwUartRx => {
+                let pin = match args.next_word() {
+                    None => return Err(args.error(None, ArgProblem::Missing("pin"))),
+                    Some(arg) => Self::parse_pin(arg).ok_or_else(|| {
+                        args.error(Some(arg), ArgProblem::Invalid("is not a pin"))
+                    })?,
+                };
+                let baud = args.number("baud", swuart::MIN_BAUD..=swuart::MAX_BAUD)?;
+                // The view may follow the baud rate directly when secs is left out
+                let secs = match args.peek() {
+                    Some("hex" | "text") => None,
+                    _ => args.opt_number(1..=60u16)?,
+                };
+                let view = args
+                    .opt_choice(&[("hex", CaptureView::Hex), ("text", CaptureView::Text)])?
+                    .unwrap_or(CaptureView::Hex);
+                CliCommand::SwUartRx(pin, baud, secs.unwrap_or(DEFAULT_SWUART_SECS), view)
+            }
+            CommandId::Pinspect => {
+                let arg = args
+                    .next_word()
+                    .ok_or_else(|| args.error(None, ArgProblem::Missing("pin")))?;
+                let pin = Self::parse_pin(arg)
+                    .ok_or_else(|| args.error(Some(arg), ArgProblem::Invalid("is not a pin")))?;
+                CliCommand::Pinspect(pin)
+            }
+            CommandId::SessionLog => {
+                let setting = args.opt_choice(&[
+                    ("on", SessionLogSetting::On),
+                    ("off", SessionLogSetting::Off),
+                    ("clear", SessionLogSetting::Clear),
+                ])?;
+                CliCommand::SessionLog(setting.unwrap_or(SessionLogSetting::Show))
+            }
+            CommandId::SessionDump => CliCommand::SessionDump,
+            CommandId::LogPolicy => {
+                let mode = args.opt_choice(&[
+                    ("overwrite", LogMode::Overwrite),
+                    ("stop", LogMode::StopWhenFull),
+                ])?;
+                match mode {
+                    None => CliCommand::LogPolicy(None),
+                    Some(mode) => {
+                        let max_entries = args.opt_number(0..=u16::MAX)?.unwrap_or(0);
+                        CliCommand::LogPolicy(Some(LogPolicy { mode, max_entries }))
                     }
-                } else {
-                    let mut msg = String::new();
-                    let _ = msg.push_str("led_off: Missing LED number");
-                    CliCommand::Unknown(msg)
                 }
             }
-            _ => {
-                let mut unknown_cmd = String::new();
-                let _ = unknown_cmd.push_str(cmd);
-                CliCommand::Unknown(unknown_cmd)
+            CommandId::StorageFsck => CliCommand::StorageFsck,
+            CommandId::Lifetime => CliCommand::Lifetime,
+            CommandId::MemBudget => CliCommand::MemBudget,
+            CommandId::Supply => match args.opt_choice(&[("low", true), ("off", false)])? {
+                None => CliCommand::Supply(None),
+                Some(false) => CliCommand::Supply(Some(0)),
+                Some(true) => CliCommand::Supply(Some(
+                    args.number("mV", MIN_LOW_SUPPLY_MV..=MAX_LOW_SUPPLY_MV)?,
+                )),
+            },
+            CommandId::Meta => {
+                let field = args.opt_choice(&[
+                    ("site", MetaField::Site),
+                    ("serial", MetaField::Serial),
+                    ("note", MetaField::Note),
+                ])?;
+                match field {
+                    None => CliCommand::Meta(MetaSetting::Show),
+                    // A quoted "clear" is stored as text
+                    Some(field) if args.remainder() == "clear" => {
+                        args.next_word();
+                        CliCommand::Meta(MetaSetting::Clear(field))
+                    }
+                    Some(field) => CliCommand::Meta(MetaSetting::Set(field, args.text("text")?)),
+                }
             }
+            CommandId::Echo => {
+                let mut echo_string = String::new();
+                let words = core::iter::from_fn(|| args.next_word()).take(MAX_ARGS);
+                for (i, word) in words.enumerate() {
+                    if i > 0 {
+                        let _ = echo_string.push(' ');
+                    }
+                    let _ = echo_string.push_str(word);
+                }
+                // Echo is free text: words past MAX_ARGS are dropped, not rejected
+                args.take_remainder();
+                CliCommand::Echo(echo_string)
+            }
+            CommandId::LedOn => CliCommand::LedOn(Self::parse_led(args)?),
+            CommandId::LedOff => CliCommand::LedOff(Self::parse_led(args)?),
+        };
+        args.finish()?;
+        Ok(command)
+    }
+
+    /// Parse a pin written `P0.03` or `0.03` into `port * 32 + pin`
+    fn parse_pin(arg: &str) -> Option<u8> {
+        let arg = arg
+            .strip_prefix('P')
+            .or_else(|| arg.strip_prefix('p'))
+            .unwrap_or(arg);
+        let (port, pin) = arg.split_once('.')?;
+        let port = port.parse::<u8>().ok()?;
+        let pin = pin.parse::<u8>().ok()?;
+        match (port, pin) {
+            (0, 0..=31) | (1, 0..=15) => Some(port * 32 + pin),
+            _ => None,
+        }
+    }
+
+    /// Parse an `aa:bb:cc:dd:ee:ff` address (most significant byte first)
+    /// into the LSB-first byte order used by the SoftDevice
+    fn parse_addr(arg: &str) -> Option<[u8; 6]> {
+        let mut addr = [0u8; 6];
+        let mut octets = arg.split(':');
+        for byte in addr.iter_mut().rev() {
+            let octet = octets.next()?;
+            if octet.len() != 2 || !octet.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            *byte = u8::from_str_radix(octet, 16).ok()?;
+        }
+        if octets.next().is_some() {
+            return None;
+        }
+        Some(addr)
+    }
+
+    /// Parse and validate the LED argument of `led_on`/`led_off`
+    fn parse_led(args: &mut Args) -> Result<LedId, ArgError> {
+        let Some(arg) = args.next_word() else {
+            return Err(args.error(None, ArgProblem::Missing("LED")));
+        };
+        match arg.parse::<u8>().ok().and_then(LedId::new) {
+            Some(led) if led.is_cli_controllable() => Ok(led),
+            Some(_) => Err(args.error(Some(arg), ArgProblem::Invalid("is not LED 3 or 4"))),
+            None => Err(args.error(Some(arg), ArgProblem::Invalid("is not an LED"))),
         }
     }
 }
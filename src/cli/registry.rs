@@ -0,0 +1,113 @@
+//! The one list of console commands
+//!
+//! The parser looks commands up here, TAB completion offers their names and
+//! `help` prints their usage and summary, so a command added to [`COMMANDS`]
+//! shows up in all three.
+
+/// Which parser arm builds a command's [`CliCommand`](super::CliCommand)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandId {
+    Help,
+    Version,
+    BuildInfo,
+    Status,
+    Uptime,
+    Date,
+    Clear,
+    Reset,
+    Echo,
+    LedOn,
+    LedOff,
+    Button,
+    Temp,
+    BtScan,
+    BtScanCfg,
+    BtName,
+    BtAddr,
+    Show,
+    Color,
+    Prompt,
+    Watch,
+    Time,
+    BenchUart,
+    EchoHex,
+    Loopback,
+    SwUartRx,
+    Pinspect,
+    SessionLog,
+    SessionDump,
+    LogPolicy,
+    Meta,
+    Supply,
+    StorageFsck,
+    Lifetime,
+    MemBudget,
+}
+
+/// A console command as the user sees it
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Argument synopsis shown by `help`, empty if the command takes none
+    pub args: &'static str,
+    pub summary: &'static str,
+    pub id: CommandId,
+}
+
+const fn spec(
+    name: &'static str,
+    args: &'static str,
+    summary: &'static str,
+    id: CommandId,
+) -> CommandSpec {
+    CommandSpec {
+        name,
+        args,
+        summary,
+        id,
+    }
+}
+
+/// Every command, in the order `help` lists them
+#[rustfmt::skip]
+pub const COMMANDS: &[CommandSpec] = &[
+    spec("help", "", "Show this help", CommandId::Help),
+    spec("version", "", "Show firmware version", CommandId::Version),
+    spec("build_info", "", "Show board, memory layout and build features", CommandId::BuildInfo),
+    spec("status", "", "Show system status", CommandId::Status),
+    spec("uptime", "", "Show system uptime", CommandId::Uptime),
+    spec("date", "[unix secs]", "Show or set the wall clock (UTC)", CommandId::Date),
+    spec("clear", "", "Clear terminal", CommandId::Clear),
+    spec("reset", "[now|bootloader|safe]", "Reset system (now: skip output flush)", CommandId::Reset),
+    spec("echo", "<text>", "Echo text back", CommandId::Echo),
+    spec("led_on", "<3|4>", "Turn on LED 3 or 4", CommandId::LedOn),
+    spec("led_off", "<3|4>", "Turn off LED 3 or 4", CommandId::LedOff),
+    spec("button", "", "Show button states", CommandId::Button),
+    spec("temp", "", "Show temperature", CommandId::Temp),
+    spec("bt_scan", "[time]", "Scan for BLE devices (default 10s)", CommandId::BtScan),
+    spec("bt_scancfg", "[<interval> <window>|default]", "Scan timing in ms", CommandId::BtScanCfg),
+    spec("bt_name", "[name]", "Show or set the BLE device name", CommandId::BtName),
+    spec("bt_addr", "[addr|factory]", "Show or set the static BLE address", CommandId::BtAddr),
+    spec("show", "[all]", "Show config, statistics and readings", CommandId::Show),
+    spec("color", "[on|off]", "Enable or disable colored output", CommandId::Color),
+    spec("prompt", "[full|compact]", "Device name prompt or a bare '>'", CommandId::Prompt),
+    spec("watch", "[secs] <cmd> [args]", "Repeat a read-only command", CommandId::Watch),
+    spec("time", "<cmd>", "Run a command and report how long it took", CommandId::Time),
+    spec("bench_uart", "<bytes> [loop]", "Measure console throughput", CommandId::BenchUart),
+    spec("echo_hex", "<hex>", "Send raw bytes, then their CRC-32", CommandId::EchoHex),
+    spec("loopback", "<count>", "Echo raw bytes back, then their CRC-32", CommandId::Loopback),
+    spec("swuart_rx", "<pin> <baud> [secs] [hex|text]", "Capture 8N1 bytes on a GPIO", CommandId::SwUartRx),
+    spec("pinspect", "<pin>", "Decode a pin's PIN_CNF, GPIOTE and latches", CommandId::Pinspect),
+    spec("session_log", "[on|off|clear]", "Log commands and responses to flash", CommandId::SessionLog),
+    spec("session_dump", "", "Print the flash session log", CommandId::SessionDump),
+    spec("log_policy", "[overwrite|stop [max]]", "Session log retention", CommandId::LogPolicy),
+    spec("meta", "[site|serial|note <text|clear>]", "Installation metadata", CommandId::Meta),
+    spec("supply", "[low <mV>|off]", "Show VDD or set the low-supply threshold", CommandId::Supply),
+    spec("storage_fsck", "", "Check and repair the config and session log", CommandId::StorageFsck),
+    spec("lifetime", "", "Show power cycles, commands and scans since first boot", CommandId::Lifetime),
+    spec("mem_budget", "", "Show RAM used by the SoftDevice, statics and stack", CommandId::MemBudget),
+];
+
+/// Look a command up by its exact name
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|spec| spec.name == name)
+}
@@ -0,0 +1,381 @@
+//! The interactive CLI loop shared by every console binary
+
+use super::parser::CommandParser;
+use super::strings::Msg;
+use super::transport::Transport;
+use super::{CliCommand, CliError, CommandHandler, LineError, ResetMode, Terminal};
+use crate::fmt_util::{write_fixed, write_hex_byte, write_num, write_percent};
+use crate::hil::{self, Outcome};
+use crate::state::APP_STATE;
+use crate::storage::{self, LogDirection};
+use crate::supply;
+use crate::system::{self, BootMode};
+use defmt::{info, warn};
+use embassy_nrf::gpio::Output;
+use embassy_time::{with_timeout, Duration, Instant, Timer};
+
+/// Bytes per transfer while benchmarking
+const BENCH_CHUNK: usize = 64;
+
+/// How long to wait for a chunk to come back in loopback mode
+const BENCH_ECHO_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// `loopback` gives up when the host stops sending for this long
+const LOOPBACK_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Back-to-back read errors after which the transport is re-initialized
+const MAX_CONSECUTIVE_ERRORS: u32 = 8;
+
+/// Read, execute and answer commands forever
+///
+/// `rx_led` (active low) is flashed for every received byte.
+pub async fn run<T: Transport>(
+    terminal: &mut Terminal<'_, T>,
+    command_handler: &mut CommandHandler<'_>,
+    mut rx_led: Option<Output<'_>>,
+) -> ! {
+    let mut consecutive_errors: u32 = 0;
+
+    loop {
+        let mut single_byte = [0u8; 1];
+
+        match terminal.transport.read(&mut single_byte).await {
+            Ok(_) => {
+                consecutive_errors = 0;
+                // Flash the RX LED briefly on activity, unless the supply is low
+                if let Some(led) = rx_led.as_mut().filter(|_| !supply::low_supply()) {
+                    led.set_low();
+                    Timer::after(Duration::from_millis(10)).await;
+                    led.set_high();
+                }
+                let ch = single_byte[0];
+
+                // Handle character and check if we got a complete command
+                match terminal.handle_char(ch).await {
+                    Ok(Some(command_line)) => {
+                        // Parse and execute the command
+                        let command = CommandParser::parse_command(&command_line);
+
+                        // Clone command for later pattern matching
+                        let command_clone = command.clone();
+
+                        let op = command_line.split_whitespace().next().unwrap_or("");
+                        let started = Instant::now();
+                        if !op.is_empty() {
+                            hil::start(op);
+                        }
+                        let mut ok = !matches!(
+                            command_clone,
+                            CliCommand::InvalidArgs(_) | CliCommand::Unknown(_)
+                        );
+
+                        match command_handler.execute_command(command).await {
+                            Ok(response) => {
+                                // Only write response if it's not empty
+                                if !response.is_empty() {
+                                    let _ = terminal.write_line(&response).await;
+                                }
+                                command_handler.log_session(&command_line, &response).await;
+                            }
+                            Err(CliError::InvalidCommand) => {
+                                ok = false;
+                                let mut message: heapless::String<64> = heapless::String::new();
+                                let _ = message.push_str(Msg::InvalidCommand.text());
+                                let _ = message.push_str(Msg::TypeHelp.text());
+                                let _ = terminal.write_line(&message).await;
+                                command_handler.log_session(&command_line, &message).await;
+                            }
+                            Err(_) => {
+                                ok = false;
+                                let message = Msg::CommandFailed.text();
+                                let _ = terminal.write_line(message).await;
+                                command_handler.log_session(&command_line, message).await;
+                            }
+                        }
+
+                        // Handle special commands that need terminal interaction
+                        match command_clone {
+                            CliCommand::Help => {
+                                let _ = terminal.show_help().await;
+                            }
+                            CliCommand::Clear => {
+                                let _ = terminal.clear_screen().await;
+                            }
+                            CliCommand::Watch(ref command_line, interval) => {
+                                run_watch(terminal, command_handler, command_line, interval).await;
+                            }
+                            CliCommand::BenchUart(bytes, loopback) => {
+                                run_bench(terminal, bytes, loopback).await;
+                            }
+                            CliCommand::SessionDump => {
+                                run_session_dump(terminal).await;
+                            }
+                            CliCommand::EchoHex(ref bytes) => {
+                                run_echo_hex(terminal, bytes).await;
+                            }
+                            CliCommand::Loopback(count) => {
+                                run_loopback(terminal, count).await;
+                            }
+                            CliCommand::Reset(mode) => {
+                                // Response has been written, now shut down cleanly
+                                let boot_mode = match mode {
+                                    ResetMode::Bootloader => BootMode::Bootloader,
+                                    ResetMode::Safe => BootMode::Safe,
+                                    _ => BootMode::Normal,
+                                };
+                                command_handler.flush_lifetime().await;
+                                system::graceful_reset(boot_mode).await;
+                            }
+                            _ => {}
+                        }
+                        // After the special commands, which do their work above
+                        if !op.is_empty() {
+                            hil::end(op, Outcome::from_ok(ok), Instant::now() - started);
+                        }
+
+                        let _ = terminal.print_prompt().await;
+                    }
+                    Ok(None) => {
+                        // Character processed but no complete command yet
+                    }
+                    Err(_) => {
+                        // Handle error
+                        let _ = terminal.write_line(Msg::InputError.text()).await;
+                        let _ = terminal.print_prompt().await;
+                    }
+                }
+            }
+            Err(e) => {
+                // No LED activity on read error (no data received)
+                record_read_error(&e);
+                consecutive_errors += 1;
+                if consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
+                    warn!(
+                        "CLI: {} console errors in a row, re-initializing",
+                        consecutive_errors
+                    );
+                    terminal.transport.recover();
+                    APP_STATE.update_stats(|s| s.console.reinits += 1);
+                    consecutive_errors = 0;
+                }
+            }
+        }
+    }
+}
+
+/// Count a failed console read under its error kind
+fn record_read_error(error: &CliError) {
+    APP_STATE.update_stats(|s| match error {
+        CliError::Line(LineError::Framing) => s.console.framing += 1,
+        CliError::Line(LineError::Parity) => s.console.parity += 1,
+        CliError::Line(LineError::Overrun) => s.console.overrun += 1,
+        CliError::Line(LineError::Break) => s.console.breaks += 1,
+        _ => s.console.other += 1,
+    });
+}
+
+/// Re-run a read-only command every `interval_secs` until a key is pressed
+async fn run_watch<T: Transport>(
+    terminal: &mut Terminal<'_, T>,
+    command_handler: &mut CommandHandler<'_>,
+    command_line: &str,
+    interval_secs: u16,
+) {
+    let mut refreshes: u32 = 0;
+    loop {
+        refreshes += 1;
+        hil::iteration("watch", refreshes);
+        let _ = terminal.clear_screen().await;
+        let _ = terminal.write_str("Every ").await;
+        let mut interval_str: heapless::String<8> = heapless::String::new();
+        let _ = write_num(&mut interval_str, interval_secs as u64);
+        let _ = terminal.write_str(&interval_str).await;
+        let _ = terminal.write_str("s: ").await;
+        let _ = terminal.write_str(command_line).await;
+        let _ = terminal.write_line("    (press any key to stop)").await;
+        let _ = terminal.write_line("").await;
+
+        let command = CommandParser::parse_command(command_line);
+        if let Ok(response) = command_handler.refresh_command(command).await {
+            let _ = terminal.write_line(&response).await;
+        }
+
+        // Wait for the next refresh, stopping early on any key press
+        let mut key = [0u8; 1];
+        let wait = Duration::from_secs(interval_secs as u64);
+        if let Ok(Ok(_)) = with_timeout(wait, terminal.transport.read(&mut key)).await {
+            break;
+        }
+    }
+}
+
+/// Byte `i` of the benchmark pattern, printable so a plain terminal can show it
+fn bench_pattern(i: u32) -> u8 {
+    b'!' + (i % 94) as u8
+}
+
+/// Stream `total` pattern bytes out of the console and report the throughput
+///
+/// In loopback mode each chunk must be echoed back by the host before the
+/// next is sent, and the echo is checked against the pattern.
+async fn run_bench<T: Transport>(terminal: &mut Terminal<'_, T>, total: u32, loopback: bool) {
+    let mut chunk = [0u8; BENCH_CHUNK];
+    let mut echo = [0u8; BENCH_CHUNK];
+    let mut sent: u32 = 0;
+    let mut mismatched: u32 = 0;
+    let mut lost: u32 = 0;
+
+    // Write to the transport directly so the TX LED delay isn't measured
+    let start = Instant::now();
+    while sent < total {
+        let len = (total - sent).min(BENCH_CHUNK as u32) as usize;
+        for (i, byte) in chunk[..len].iter_mut().enumerate() {
+            *byte = bench_pattern(sent + i as u32);
+        }
+        if terminal.transport.write(&chunk[..len]).await.is_err() {
+            break;
+        }
+        if loopback {
+            match with_timeout(
+                BENCH_ECHO_TIMEOUT,
+                terminal.transport.read(&mut echo[..len]),
+            )
+            .await
+            {
+                Ok(Ok(())) => {
+                    mismatched += chunk[..len]
+                        .iter()
+                        .zip(&echo[..len])
+                        .filter(|(expected, received)| expected != received)
+                        .count() as u32;
+                }
+                _ => lost += len as u32,
+            }
+        }
+        sent += len as u32;
+    }
+    let elapsed_us = (Instant::now() - start).as_micros().max(1);
+    info!("CLI: bench sent {} bytes in {} us", sent, elapsed_us);
+    if loopback {
+        hil::summary(
+            "bench_uart",
+            sent.saturating_sub(mismatched + lost),
+            mismatched + lost,
+        );
+    }
+
+    let mut report: heapless::String<128> = heapless::String::new();
+    let _ = report.push_str("\r\nSent ");
+    let _ = write_num(&mut report, sent as u64);
+    let _ = report.push_str(" bytes in ");
+    let _ = write_fixed(&mut report, elapsed_us as i64, 3);
+    let _ = report.push_str(" ms (");
+    let _ = write_num(&mut report, sent as u64 * 1_000_000 / elapsed_us);
+    let _ = report.push_str(" B/s)");
+    if loopback {
+        let _ = report.push_str("\r\nLoopback: ");
+        let _ = write_num(&mut report, mismatched as u64);
+        let _ = report.push_str(" corrupted, ");
+        let _ = write_num(&mut report, lost as u64);
+        let _ = report.push_str(" lost (");
+        let _ = write_percent(
+            &mut report,
+            sent.saturating_sub(mismatched + lost) as u64,
+            sent as u64,
+        );
+        let _ = report.push_str(" ok)");
+    }
+    let _ = terminal.write_line(&report).await;
+}
+
+/// Print every line in the flash session log, oldest first
+async fn run_session_dump<T: Transport>(terminal: &mut Terminal<'_, T>) {
+    // Identify the unit so dumps collected from several bridges can be told apart
+    let config = APP_STATE.config();
+    for (label, value) in [
+        ("# Site: ", &config.site),
+        ("# Serial: ", &config.meter_serial),
+    ] {
+        if !value.is_empty() {
+            let _ = terminal.write_str(label).await;
+            let _ = terminal.write_line(value).await;
+        }
+    }
+
+    let mut lines: u32 = 0;
+    for entry in storage::session_log() {
+        // Records are cut on a character boundary, so this only fails on corruption
+        let text = core::str::from_utf8(entry.text).unwrap_or("(unreadable record)");
+        if let Some(uptime) = entry.uptime {
+            // Seconds since boot, as in the defmt log
+            let mut stamp: heapless::String<24> = heapless::String::new();
+            let _ = stamp.push('[');
+            let _ = write_fixed(&mut stamp, uptime.as_micros() as i64, 6);
+            let _ = stamp.push_str("] ");
+            let _ = terminal.write_str(&stamp).await;
+        }
+        if entry.direction == LogDirection::Input {
+            let _ = terminal.write_str("> ").await;
+        }
+        let _ = terminal.write_line(text).await;
+        lines += 1;
+    }
+    info!("CLI: Session log dumped ({} lines)", lines);
+
+    let mut summary: heapless::String<32> = heapless::String::new();
+    let _ = write_num(&mut summary, lines as u64);
+    let _ = summary.push_str(" lines");
+    let _ = terminal.write_line(&summary).await;
+}
+
+/// `N bytes, CRC-32 xxxxxxxx` for the raw transfer commands
+async fn write_crc_report<T: Transport>(terminal: &mut Terminal<'_, T>, bytes: u32, crc: u32) {
+    let mut report: heapless::String<48> = heapless::String::new();
+    let _ = report.push_str("\r\n");
+    let _ = write_num(&mut report, bytes as u64);
+    let _ = report.push_str(" bytes, CRC-32 ");
+    for byte in crc.to_be_bytes() {
+        let _ = write_hex_byte(&mut report, byte);
+    }
+    let _ = terminal.write_line(&report).await;
+}
+
+/// Write `bytes` to the transport unchanged, then their CRC-32
+async fn run_echo_hex<T: Transport>(terminal: &mut Terminal<'_, T>, bytes: &[u8]) {
+    // Straight to the transport; write_str would only take UTF-8
+    let _ = terminal.transport.write(bytes).await;
+    let crc = storage::crc32(bytes);
+    info!("CLI: echo_hex sent {} bytes, crc {:08x}", bytes.len(), crc);
+    write_crc_report(terminal, bytes.len() as u32, crc).await;
+}
+
+/// Read `count` raw bytes, echoing each one back, then report their CRC-32
+///
+/// No line editing happens meanwhile, so any byte value passes through.
+/// Stops early if the host goes quiet for [`LOOPBACK_IDLE_TIMEOUT`].
+async fn run_loopback<T: Transport>(terminal: &mut Terminal<'_, T>, count: u16) {
+    let mut crc = !0;
+    let mut received: u32 = 0;
+    let mut byte = [0u8; 1];
+    while received < count as u32 {
+        match with_timeout(LOOPBACK_IDLE_TIMEOUT, terminal.transport.read(&mut byte)).await {
+            Ok(Ok(())) => {}
+            _ => break,
+        }
+        crc = storage::crc32_update(crc, &byte);
+        received += 1;
+        if terminal.transport.write(&byte).await.is_err() {
+            break;
+        }
+    }
+    let crc = !crc;
+    info!(
+        "CLI: loopback echoed {}/{} bytes, crc {:08x}",
+        received, count, crc
+    );
+    hil::summary("loopback", received, count as u32 - received);
+    if received < count as u32 {
+        let _ = terminal.write_str("\r\nTimed out").await;
+    }
+    write_crc_report(terminal, received, crc).await;
+}
@@ -0,0 +1,204 @@
+//! User-facing CLI messages
+//!
+//! Responses refer to fixed messages by [`Msg`] instead of spelling out the
+//! English text. The table is picked at build time: the default is full
+//! sentences, `--features terse-strings` swaps in short forms for small
+//! screens and logs. [`Msg::code`] gives each message a number that stays
+//! the same across tables and releases, for tools that parse the output.
+
+/// A fixed message shown by the CLI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Msg {
+    HelpDisplayed,
+    ScreenCleared,
+    Resetting,
+    ResettingBootloader,
+    ResettingSafe,
+    TempReadFailed,
+    ScanFailed,
+    NameSetFailed,
+    FactoryAddrAfterReset,
+    PinInUse,
+    SessionLogNeedsStorage,
+    SessionLogEraseFailed,
+    NotCountingSafeMode,
+    StorageUnavailableSafeMode,
+    StorageCheckFailed,
+    NotSaved,
+    UnknownCommand,
+    TypeHelp,
+    ArgMissing,
+    ArgNotANumber,
+    ArgOutOfRange,
+    ArgNotAChoice,
+    ArgTooLong,
+    Usage,
+    ArgUnexpected,
+    CommandFailed,
+    InputError,
+    InvalidCommand,
+    UptimeLabel,
+    DateLabel,
+    ButtonStatesHeading,
+    TemperatureLabel,
+    ScanTimingLabel,
+    ScanTimingDefault,
+    DeviceNameLabel,
+    DeviceAddressLabel,
+    ColorOutputLabel,
+    PromptLabel,
+    SessionLogLabel,
+}
+
+impl Msg {
+    /// Stable identifier; new messages get new numbers, old ones never change
+    ///
+    /// Numbers below 100 are confirmations, 100-199 failures, 200-299
+    /// rejected input and 300 up the labels that start a command's output.
+    pub const fn code(self) -> u16 {
+        match self {
+            Msg::HelpDisplayed => 1,
+            Msg::ScreenCleared => 2,
+            Msg::Resetting => 3,
+            Msg::ResettingBootloader => 4,
+            Msg::ResettingSafe => 5,
+            Msg::TempReadFailed => 100,
+            Msg::ScanFailed => 101,
+            Msg::NameSetFailed => 102,
+            Msg::FactoryAddrAfterReset => 103,
+            Msg::PinInUse => 104,
+            Msg::SessionLogNeedsStorage => 105,
+            Msg::SessionLogEraseFailed => 106,
+            Msg::NotCountingSafeMode => 107,
+            Msg::StorageUnavailableSafeMode => 108,
+            Msg::StorageCheckFailed => 109,
+            Msg::NotSaved => 110,
+            Msg::UnknownCommand => 200,
+            Msg::TypeHelp => 201,
+            Msg::ArgMissing => 202,
+            Msg::ArgNotANumber => 203,
+            Msg::ArgOutOfRange => 204,
+            Msg::ArgNotAChoice => 205,
+            Msg::ArgTooLong => 206,
+            Msg::Usage => 207,
+            Msg::ArgUnexpected => 208,
+            Msg::CommandFailed => 111,
+            Msg::InputError => 112,
+            Msg::InvalidCommand => 209,
+            Msg::UptimeLabel => 300,
+            Msg::DateLabel => 301,
+            Msg::ButtonStatesHeading => 302,
+            Msg::TemperatureLabel => 303,
+            Msg::ScanTimingLabel => 304,
+            Msg::ScanTimingDefault => 305,
+            Msg::DeviceNameLabel => 306,
+            Msg::DeviceAddressLabel => 307,
+            Msg::ColorOutputLabel => 308,
+            Msg::PromptLabel => 309,
+            Msg::SessionLogLabel => 310,
+        }
+    }
+
+    /// Text of the message in the table this image was built with
+    pub const fn text(self) -> &'static str {
+        table::text(self)
+    }
+}
+
+#[cfg(not(feature = "terse-strings"))]
+mod table {
+    use super::Msg;
+
+    pub const fn text(msg: Msg) -> &'static str {
+        match msg {
+            Msg::HelpDisplayed => "Help displayed",
+            Msg::ScreenCleared => "Screen cleared",
+            Msg::Resetting => "Resetting system...",
+            Msg::ResettingBootloader => "Resetting into DFU bootloader...",
+            Msg::ResettingSafe => "Resetting into safe mode...",
+            Msg::TempReadFailed => "Failed to read temperature sensor",
+            Msg::ScanFailed => "BLE scan failed",
+            Msg::NameSetFailed => "Failed to set device name",
+            Msg::FactoryAddrAfterReset => "Factory address used after reset",
+            Msg::PinInUse => "Pin is in use on this board",
+            Msg::SessionLogNeedsStorage => "Session log needs flash storage",
+            Msg::SessionLogEraseFailed => "Failed to erase the session log",
+            Msg::NotCountingSafeMode => "Not counting (safe mode)",
+            Msg::StorageUnavailableSafeMode => "Storage unavailable (safe mode)",
+            Msg::StorageCheckFailed => "Storage check failed: flash error",
+            Msg::NotSaved => "Warning: setting not saved to flash",
+            Msg::UnknownCommand => "Unknown command: ",
+            Msg::TypeHelp => ". Type 'help' for available commands.",
+            Msg::ArgMissing => "missing ",
+            Msg::ArgNotANumber => "is not a number",
+            Msg::ArgOutOfRange => "is outside ",
+            Msg::ArgNotAChoice => "is not an option",
+            Msg::ArgTooLong => "text too long (max ",
+            Msg::Usage => "Usage: ",
+            Msg::ArgUnexpected => "is unexpected",
+            Msg::CommandFailed => "Command execution error.",
+            Msg::InputError => "Input error",
+            Msg::InvalidCommand => "Invalid command",
+            Msg::UptimeLabel => "Uptime: ",
+            Msg::DateLabel => "Date: ",
+            Msg::ButtonStatesHeading => "Button States:",
+            Msg::TemperatureLabel => "Temperature: ",
+            Msg::ScanTimingLabel => "Scan timing: ",
+            Msg::ScanTimingDefault => "SoftDevice default",
+            Msg::DeviceNameLabel => "Device name: ",
+            Msg::DeviceAddressLabel => "Device address: ",
+            Msg::ColorOutputLabel => "Color output: ",
+            Msg::PromptLabel => "Prompt: ",
+            Msg::SessionLogLabel => "Session log: ",
+        }
+    }
+}
+
+#[cfg(feature = "terse-strings")]
+mod table {
+    use super::Msg;
+
+    pub const fn text(msg: Msg) -> &'static str {
+        match msg {
+            Msg::HelpDisplayed => "OK",
+            Msg::ScreenCleared => "OK",
+            Msg::Resetting => "Reset",
+            Msg::ResettingBootloader => "Reset: DFU",
+            Msg::ResettingSafe => "Reset: safe",
+            Msg::TempReadFailed => "E: temp",
+            Msg::ScanFailed => "E: scan",
+            Msg::NameSetFailed => "E: name",
+            Msg::FactoryAddrAfterReset => "Factory addr after reset",
+            Msg::PinInUse => "E: pin in use",
+            Msg::SessionLogNeedsStorage => "E: no storage",
+            Msg::SessionLogEraseFailed => "E: erase",
+            Msg::NotCountingSafeMode => "Safe mode",
+            Msg::StorageUnavailableSafeMode => "Safe mode",
+            Msg::StorageCheckFailed => "E: flash",
+            Msg::NotSaved => "W: not saved",
+            Msg::UnknownCommand => "E: unknown ",
+            Msg::TypeHelp => "",
+            Msg::ArgMissing => "missing ",
+            Msg::ArgNotANumber => "NaN",
+            Msg::ArgOutOfRange => "not ",
+            Msg::ArgNotAChoice => "invalid",
+            Msg::ArgTooLong => "too long (max ",
+            Msg::Usage => "Use: ",
+            Msg::ArgUnexpected => "extra",
+            Msg::CommandFailed => "E: failed",
+            Msg::InputError => "E: input",
+            Msg::InvalidCommand => "E: invalid",
+            Msg::UptimeLabel => "Up: ",
+            Msg::DateLabel => "Date: ",
+            Msg::ButtonStatesHeading => "Buttons:",
+            Msg::TemperatureLabel => "Temp: ",
+            Msg::ScanTimingLabel => "Scan: ",
+            Msg::ScanTimingDefault => "default",
+            Msg::DeviceNameLabel => "Name: ",
+            Msg::DeviceAddressLabel => "Addr: ",
+            Msg::ColorOutputLabel => "Color: ",
+            Msg::PromptLabel => "Prompt: ",
+            Msg::SessionLogLabel => "Log: ",
+        }
+    }
+}
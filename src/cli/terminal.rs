@@ -1,16 +1,26 @@
-use super::{parser::CommandParser, CliError, CLI_BUFFER_SIZE};
-use embassy_nrf::{gpio::Output, uarte::Uarte};
+use super::{
+    parser::CommandParser, registry::COMMANDS, transport::Transport, CliError, CLI_BUFFER_SIZE,
+    MAX_ARGS, MAX_HISTORY_SIZE, RESPONSE_SIZE, TX_CHUNK_SIZE,
+};
+use crate::state::APP_STATE;
+use crate::supply;
+use embassy_nrf::gpio::Output;
 use embassy_time::{Duration, Timer};
 use heapless::{String, Vec};
 
-const HISTORY_SIZE: usize = 10;
+/// `help` pads "  name args" to this width before the summary
+const HELP_USAGE_WIDTH: usize = 13;
 
-pub struct Terminal<'d> {
-    pub uart: Uarte<'d, embassy_nrf::peripherals::UARTE1>,
+/// Prompt name used until the binary gives its own with `with_hostname`
+const DEFAULT_HOSTNAME: &str = "nRF52840-DK CLI";
+
+pub struct Terminal<'d, T: Transport> {
+    pub transport: T,
     tx_led: Option<Output<'d>>,
+    hostname: &'static str,
     line_buffer: String<CLI_BUFFER_SIZE>,
     cursor_pos: usize,
-    command_history: Vec<String<CLI_BUFFER_SIZE>, HISTORY_SIZE>,
+    command_history: Vec<String<CLI_BUFFER_SIZE>, MAX_HISTORY_SIZE>,
     history_index: Option<usize>,
     escape_state: EscapeState,
 }
@@ -22,11 +32,12 @@ enum EscapeState {
     Csi,
 }
 
-impl<'d> Terminal<'d> {
-    pub fn new(uart: Uarte<'d, embassy_nrf::peripherals::UARTE1>) -> Self {
+impl<'d, T: Transport> Terminal<'d, T> {
+    pub fn new(transport: T) -> Self {
         Self {
-            uart,
+            transport,
             tx_led: None,
+            hostname: DEFAULT_HOSTNAME,
             line_buffer: String::new(),
             cursor_pos: 0,
             command_history: Vec::new(),
@@ -40,22 +51,29 @@ impl<'d> Terminal<'d> {
         self
     }
 
+    /// Built-in name for the prompt, shown while no device name is saved
+    pub fn with_hostname(mut self, hostname: &'static str) -> Self {
+        self.hostname = hostname;
+        self
+    }
+
     pub async fn write_str(&mut self, s: &str) -> Result<(), CliError> {
-        // Flash TX LED during transmission if available
-        if let Some(ref mut led) = self.tx_led {
+        // Flash TX LED during transmission if available, unless the supply is low
+        let mut tx_led = self.tx_led.as_mut().filter(|_| !supply::low_supply());
+        if let Some(led) = tx_led.as_mut() {
             led.set_low(); // Turn on LED (active low)
         }
 
-        // Send each character individually to debug transmission
-        for &byte in s.as_bytes() {
-            self.uart
-                .write(&[byte])
-                .await
-                .map_err(|_| CliError::UartError)?;
+        // EasyDMA only reads RAM, so text in flash goes through a stack
+        // buffer, one transfer per chunk rather than per byte
+        let mut chunk = [0u8; TX_CHUNK_SIZE];
+        for piece in s.as_bytes().chunks(TX_CHUNK_SIZE) {
+            chunk[..piece.len()].copy_from_slice(piece);
+            self.transport.write(&chunk[..piece.len()]).await?;
         }
 
         // Small delay to make TX flash visible, then turn off TX LED
-        if let Some(ref mut led) = self.tx_led {
+        if let Some(led) = tx_led {
             Timer::after(Duration::from_millis(10)).await;
             led.set_high(); // Turn off LED (active low)
         }
@@ -68,8 +86,18 @@ impl<'d> Terminal<'d> {
         self.write_str("\r\n").await
     }
 
+    /// `<name>> `, using the device name set with `bt_name` if there is one,
+    /// or just `> ` in compact mode
     pub async fn print_prompt(&mut self) -> Result<(), CliError> {
-        self.write_str("nRF52840-DK CLI> ").await
+        let config = APP_STATE.config();
+        if !config.compact_prompt {
+            if config.device_name.is_empty() {
+                self.write_str(self.hostname).await?;
+            } else {
+                self.write_str(&config.device_name).await?;
+            }
+        }
+        self.write_str("> ").await
     }
 
     pub async fn handle_char(
@@ -89,7 +117,7 @@ impl<'d> Terminal<'d> {
                             || self.command_history.last() != Some(&command);
 
                         if should_add {
-                            if self.command_history.len() >= HISTORY_SIZE {
+                            if self.command_history.len() >= MAX_HISTORY_SIZE {
                                 self.command_history.remove(0);
                             }
                             let _ = self.command_history.push(command.clone());
@@ -191,7 +219,7 @@ impl<'d> Terminal<'d> {
         // Clone the current line to avoid borrowing issues
         let current_line: String<CLI_BUFFER_SIZE> = self.line_buffer.clone();
         let current_line_str = current_line.as_str();
-        let words: heapless::Vec<&str, 8> = current_line_str.split_whitespace().collect();
+        let words: heapless::Vec<&str, MAX_ARGS> = current_line_str.split_whitespace().collect();
 
         // Only autocomplete the first word (command)
         if words.is_empty() || (!current_line_str.ends_with(' ') && words.len() == 1) {
@@ -222,10 +250,7 @@ impl<'d> Terminal<'d> {
                         {
                             self.cursor_pos += 1;
                             let echo = [ch as u8];
-                            self.uart
-                                .write(&echo)
-                                .await
-                                .map_err(|_| CliError::UartError)?;
+                            self.transport.write(&echo).await?;
                         }
                     }
                     // Add a space after completion
@@ -233,10 +258,7 @@ impl<'d> Terminal<'d> {
                         && self.line_buffer.push(' ').is_ok()
                     {
                         self.cursor_pos += 1;
-                        self.uart
-                            .write(b" ")
-                            .await
-                            .map_err(|_| CliError::UartError)?;
+                        self.transport.write(b" ").await?;
                     }
                 }
                 _ => {
@@ -260,25 +282,22 @@ impl<'d> Terminal<'d> {
 
     pub async fn show_help(&mut self) -> Result<(), CliError> {
         self.write_line("Available commands:").await?;
-        self.write_line("  help        - Show this help").await?;
-        self.write_line("  version     - Show firmware version")
-            .await?;
-        self.write_line("  status      - Show system status")
-            .await?;
-        self.write_line("  uptime      - Show system uptime")
-            .await?;
-        self.write_line("  clear       - Clear terminal").await?;
-        self.write_line("  reset       - Reset system").await?;
-        self.write_line("  echo <text> - Echo text back").await?;
-        self.write_line("  led_on <3|4>  - Turn on LED 3 or 4")
-            .await?;
-        self.write_line("  led_off <3|4> - Turn off LED 3 or 4")
-            .await?;
-        self.write_line("  button      - Show button states")
-            .await?;
-        self.write_line("  temp        - Show temperature").await?;
-        self.write_line("  bt_scan [time] - Scan for BLE devices (default 10s)")
-            .await?;
+        for spec in COMMANDS {
+            let mut line: String<RESPONSE_SIZE> = String::new();
+            let _ = line.push_str("  ");
+            let _ = line.push_str(spec.name);
+            if !spec.args.is_empty() {
+                let _ = line.push(' ');
+                let _ = line.push_str(spec.args);
+            }
+            // Short usages line up their summaries
+            while line.len() < HELP_USAGE_WIDTH {
+                let _ = line.push(' ');
+            }
+            let _ = line.push_str(" - ");
+            let _ = line.push_str(spec.summary);
+            self.write_line(&line).await?;
+        }
         self.write_line("").await?;
         self.write_line("Use TAB to autocomplete commands").await?;
         self.write_line("Use UP/DOWN arrows to navigate command history")
@@ -379,10 +398,7 @@ impl<'d> Terminal<'d> {
                 self.cursor_pos += 1;
                 // Echo the character
                 let echo = [ch as u8];
-                self.uart
-                    .write(&echo)
-                    .await
-                    .map_err(|_| CliError::UartError)?;
+                self.transport.write(&echo).await?;
             }
         } else {
             // Complex case: inserting in middle - need to rebuild string
@@ -430,10 +446,7 @@ impl<'d> Terminal<'d> {
         // Write the characters from cursor position onward
         for ch in chars_to_redraw.iter() {
             let echo = [*ch as u8];
-            self.uart
-                .write(&echo)
-                .await
-                .map_err(|_| CliError::UartError)?;
+            self.transport.write(&echo).await?;
         }
 
         // Move cursor back to correct position
@@ -491,10 +504,7 @@ impl<'d> Terminal<'d> {
         // Write the characters from cursor position onward
         for ch in chars_to_redraw.iter() {
             let echo = [*ch as u8];
-            self.uart
-                .write(&echo)
-                .await
-                .map_err(|_| CliError::UartError)?;
+            self.transport.write(&echo).await?;
         }
 
         // Clear the extra character that was there before
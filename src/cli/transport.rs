@@ -0,0 +1,148 @@
+//! Byte streams the CLI terminal can run over
+//!
+//! The DK uses UARTE1; the Dongle has no UART bridge and talks over USB
+//! CDC-ACM instead. Both look the same to [`Terminal`](super::Terminal).
+
+use super::{CliError, LineError};
+use embassy_nrf::pac;
+use embassy_nrf::pac::uarte::vals::Enable;
+use embassy_nrf::peripherals::UARTE1;
+use embassy_nrf::uarte::{self, Uarte};
+use embassy_time::{Duration, Instant};
+use embassy_usb::class::cdc_acm::CdcAcmClass;
+use embassy_usb::driver::{Driver, EndpointError};
+
+/// Longest wait in [`Transport::recover`] for UARTE1 to confirm a stop;
+/// RXTO follows within a few byte times even at low baud rates
+const STOP_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Full-speed bulk endpoint size
+const USB_PACKET_SIZE: usize = 64;
+
+/// A bidirectional byte stream carrying a CLI session
+#[allow(async_fn_in_trait)]
+pub trait Transport {
+    /// Read exactly `buf.len()` bytes
+    async fn read(&mut self, buf: &mut [u8]) -> Result<(), CliError>;
+
+    /// Write all of `buf`
+    async fn write(&mut self, buf: &[u8]) -> Result<(), CliError>;
+
+    /// Bring the link back to a clean state after repeated read errors
+    fn recover(&mut self) {}
+}
+
+impl Transport for Uarte<'_, UARTE1> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<(), CliError> {
+        Uarte::read(self, buf).await.map_err(|e| match e {
+            uarte::Error::Framing => CliError::Line(LineError::Framing),
+            uarte::Error::Parity => CliError::Line(LineError::Parity),
+            uarte::Error::Overrun => CliError::Line(LineError::Overrun),
+            uarte::Error::Break => CliError::Line(LineError::Break),
+            _ => CliError::UartError,
+        })
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> Result<(), CliError> {
+        Uarte::write(self, buf)
+            .await
+            .map_err(|_| CliError::UartError)
+    }
+
+    /// Stop the receiver, clear the latched error flags and power-cycle the peripheral
+    ///
+    /// Only called between transfers, so no DMA is in flight. The driver keeps
+    /// no copy of these registers and picks up where it left off. Disabling
+    /// before RXTO (or TXSTOPPED, if a transmission was still going) would
+    /// cut the stop short, so each wait is bounded to keep a wedged
+    /// peripheral from hanging the console.
+    fn recover(&mut self) {
+        let regs = pac::UARTE1;
+        let tx_active = regs.events_txstarted().read() != 0 && regs.events_endtx().read() == 0;
+        if tx_active {
+            regs.events_txstopped().write_value(0);
+            regs.tasks_stoptx().write_value(1);
+        }
+        regs.events_rxto().write_value(0);
+        regs.tasks_stoprx().write_value(1);
+
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        while regs.events_rxto().read() == 0 && Instant::now() < deadline {}
+        regs.events_rxto().write_value(0);
+        if tx_active {
+            while regs.events_txstopped().read() == 0 && Instant::now() < deadline {}
+            regs.events_txstopped().write_value(0);
+        }
+
+        // ERRORSRC is write-one-to-clear
+        regs.errorsrc().write_value(regs.errorsrc().read());
+        regs.events_error().write_value(0);
+        regs.enable().write(|w| w.set_enable(Enable::DISABLED));
+        regs.enable().write(|w| w.set_enable(Enable::ENABLED));
+    }
+}
+
+/// USB CDC-ACM serial port
+///
+/// Bytes arrive in packets, so reads are buffered here to support the
+/// terminal's byte-at-a-time input.
+pub struct UsbSerial<'d, D: Driver<'d>> {
+    class: CdcAcmClass<'d, D>,
+    rx_buf: [u8; USB_PACKET_SIZE],
+    rx_pos: usize,
+    rx_len: usize,
+}
+
+impl<'d, D: Driver<'d>> UsbSerial<'d, D> {
+    pub fn new(class: CdcAcmClass<'d, D>) -> Self {
+        Self {
+            class,
+            rx_buf: [0; USB_PACKET_SIZE],
+            rx_pos: 0,
+            rx_len: 0,
+        }
+    }
+
+    /// Wait until a host opens the port
+    pub async fn wait_connection(&mut self) {
+        self.class.wait_connection().await;
+    }
+}
+
+impl<'d, D: Driver<'d>> Transport for UsbSerial<'d, D> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<(), CliError> {
+        for byte in buf.iter_mut() {
+            while self.rx_pos == self.rx_len {
+                match self.class.read_packet(&mut self.rx_buf).await {
+                    Ok(len) => {
+                        self.rx_pos = 0;
+                        self.rx_len = len;
+                    }
+                    // Host closed the port or the cable was pulled
+                    Err(EndpointError::Disabled) => self.class.wait_connection().await,
+                    Err(EndpointError::BufferOverflow) => return Err(CliError::BufferFull),
+                }
+            }
+            *byte = self.rx_buf[self.rx_pos];
+            self.rx_pos += 1;
+        }
+        Ok(())
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> Result<(), CliError> {
+        for chunk in buf.chunks(USB_PACKET_SIZE) {
+            self.class
+                .write_packet(chunk)
+                .await
+                .map_err(|_| CliError::UartError)?;
+        }
+        // A transfer ending on a full packet must be terminated by a zero-length packet
+        if !buf.is_empty() && buf.len() % USB_PACKET_SIZE == 0 {
+            self.class
+                .write_packet(&[])
+                .await
+                .map_err(|_| CliError::UartError)?;
+        }
+        Ok(())
+    }
+}
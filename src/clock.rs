@@ -0,0 +1,82 @@
+//! The firmware's single time base
+//!
+//! defmt log timestamps, `uptime` and `date` all read time from here, so a
+//! log line and a CLI report of the same moment agree. Boot-relative time is
+//! the embassy-time tick count, which starts at zero with the time driver.
+//! Wall-clock time exists once the host sets it with `date`: it is kept as
+//! the Unix time of boot, so it advances with the same tick, and it is lost
+//! on reset.
+
+use core::cell::Cell;
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use embassy_time::{Duration, Instant};
+
+#[cfg(not(feature = "std"))]
+defmt::timestamp!("{=u64:us}", Instant::now().as_micros());
+
+/// Unix time at boot in seconds, `None` until the wall clock is set
+static BOOT_UNIX_SECS: Mutex<CriticalSectionRawMutex, Cell<Option<u64>>> =
+    Mutex::new(Cell::new(None));
+
+/// Time since boot
+pub fn uptime() -> Duration {
+    Duration::from_ticks(Instant::now().as_ticks())
+}
+
+/// Seconds since the Unix epoch, `None` if the wall clock was never set
+pub fn unix_time() -> Option<u64> {
+    BOOT_UNIX_SECS
+        .lock(|boot| boot.get())
+        .map(|boot| boot + uptime().as_secs())
+}
+
+/// Set the wall clock so that it reads `unix_secs` now
+pub fn set_unix_time(unix_secs: u64) {
+    let boot = unix_secs.saturating_sub(uptime().as_secs());
+    BOOT_UNIX_SECS.lock(|cell| cell.set(Some(boot)));
+}
+
+/// A UTC calendar date and time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct DateTime {
+    pub year: u32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Split Unix seconds into a date in the proleptic Gregorian calendar
+    pub fn from_unix(secs: u64) -> Self {
+        let days = secs / 86_400;
+        let time = secs % 86_400;
+
+        // Days to civil date with eras of 400 years starting on 1 March,
+        // which puts the leap day at the end of each year
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let march_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * march_month + 2) / 5 + 1;
+        let month = if march_month < 10 {
+            march_month + 3
+        } else {
+            march_month - 9
+        };
+        let year = era * 400 + year_of_era + (month <= 2) as u64;
+
+        Self {
+            year: year as u32,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time % 3600 / 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Numeric formatting helpers
+//!
+//! Allocation-free number formatting into `heapless::String` of any capacity,
+//! without pulling in `core::fmt` machinery. All helpers return `Err(())`
+//! when the string is full.
+
+use crate::clock::DateTime;
+use heapless::String;
+
+/// Write an unsigned decimal number
+pub fn write_num<const N: usize>(s: &mut String<N>, mut num: u64) -> Result<(), ()> {
+    if num == 0 {
+        return s.push('0');
+    }
+
+    let mut digits = heapless::Vec::<u8, 20>::new();
+    while num > 0 {
+        let _ = digits.push((num % 10) as u8);
+        num /= 10;
+    }
+
+    for &digit in digits.iter().rev() {
+        s.push((b'0' + digit) as char)?;
+    }
+
+    Ok(())
+}
+
+/// Write a signed decimal number
+pub fn write_signed<const N: usize>(s: &mut String<N>, num: i64) -> Result<(), ()> {
+    if num < 0 {
+        s.push('-')?;
+    }
+    write_num(s, num.unsigned_abs())
+}
+
+/// Write a byte as two lowercase hex digits
+pub fn write_hex_byte<const N: usize>(s: &mut String<N>, byte: u8) -> Result<(), ()> {
+    let hex_chars = b"0123456789abcdef";
+    s.push(hex_chars[(byte >> 4) as usize] as char)?;
+    s.push(hex_chars[(byte & 0x0f) as usize] as char)
+}
+
+/// Write bytes as text, printable ASCII as is and anything else as `\xNN`
+///
+/// A backslash is doubled so escapes stay unambiguous. `\r` comes out as
+/// `\x0d`, which makes line endings and padding bytes visible.
+pub fn write_escaped<const N: usize>(s: &mut String<N>, bytes: &[u8]) -> Result<(), ()> {
+    for &byte in bytes {
+        match byte {
+            b'\\' => s.push_str("\\\\")?,
+            0x20..=0x7e => s.push(byte as char)?,
+            _ => {
+                s.push_str("\\x")?;
+                write_hex_byte(s, byte)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write a fixed-point value given in units of 10^-`decimals`
+///
+/// `write_fixed(s, -1234, 2)` writes `-12.34`.
+pub fn write_fixed<const N: usize>(s: &mut String<N>, value: i64, decimals: u32) -> Result<(), ()> {
+    if value < 0 {
+        s.push('-')?;
+    }
+    let magnitude = value.unsigned_abs();
+    let scale = 10u64.pow(decimals);
+    write_num(s, magnitude / scale)?;
+
+    if decimals > 0 {
+        s.push('.')?;
+        let frac = magnitude % scale;
+        // Leading zeros of the fractional part
+        let mut divisor = scale / 10;
+        while divisor > 1 && frac < divisor {
+            s.push('0')?;
+            divisor /= 10;
+        }
+        write_num(s, frac)?;
+    }
+
+    Ok(())
+}
+
+/// Write a temperature rounded to one decimal place, e.g. `23.5°C`
+pub fn write_temperature<const N: usize>(s: &mut String<N>, celsius: f32) -> Result<(), ()> {
+    let tenths = if celsius < 0.0 {
+        (celsius * 10.0 - 0.5) as i64
+    } else {
+        (celsius * 10.0 + 0.5) as i64
+    };
+    write_fixed(s, tenths, 1)?;
+    s.push_str("°C")
+}
+
+/// Write `part / total` as a percentage with one decimal place, e.g. `97.5%`
+///
+/// Writes `n/a` when `total` is zero.
+pub fn write_percent<const N: usize>(s: &mut String<N>, part: u64, total: u64) -> Result<(), ()> {
+    if total == 0 {
+        return s.push_str("n/a");
+    }
+    // Tenths of a percent, rounded to nearest
+    let tenths = (part.saturating_mul(2000) / total).div_ceil(2);
+    write_fixed(s, tenths as i64, 1)?;
+    s.push('%')
+}
+
+/// Write a date and time as `2024-05-17 09:03:07`
+pub fn write_datetime<const N: usize>(s: &mut String<N>, dt: &DateTime) -> Result<(), ()> {
+    write_num(s, dt.year as u64)?;
+    for (sep, value) in [
+        ('-', dt.month),
+        ('-', dt.day),
+        (' ', dt.hour),
+        (':', dt.minute),
+        (':', dt.second),
+    ] {
+        s.push(sep)?;
+        if value < 10 {
+            s.push('0')?;
+        }
+        write_num(s, value as u64)?;
+    }
+    Ok(())
+}
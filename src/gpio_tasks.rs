@@ -1,17 +1,47 @@
+//! LED and button tasks shared by the GPIO demo binaries
+//!
+//! [`spawn_standard_tasks`] sets up the heartbeat, the button 1 → LED 2
+//! mirror and the LED 3/4 pattern in one call; the tasks are public too for
+//! binaries that want only some of them.
+
 use defmt::*;
 use embassy_executor::task;
 use embassy_nrf::gpio::{Input, Output};
 use embassy_time::{Duration, Timer};
 
+/// Heartbeat LED on time in each period
+const HEARTBEAT_ON: Duration = Duration::from_millis(100);
+
+/// Blink rates of the standard tasks
+#[derive(Debug, Clone, Copy)]
+pub struct Timing {
+    /// Heartbeat repeat period, [`HEARTBEAT_ON`] of it lit
+    pub heartbeat_period: Duration,
+    /// How long each LED of the alternating pattern stays lit
+    pub pattern_step: Duration,
+}
+
+impl Timing {
+    /// 2 Hz heartbeat and a slow pattern, used by the GPIO-only app
+    pub const RELAXED: Self = Self {
+        heartbeat_period: Duration::from_millis(500),
+        pattern_step: Duration::from_millis(1000),
+    };
+    /// 1 Hz heartbeat and a quick pattern, used alongside the SoftDevice
+    pub const BRISK: Self = Self {
+        heartbeat_period: Duration::from_millis(1000),
+        pattern_step: Duration::from_millis(300),
+    };
+}
+
 #[task]
-pub async fn heartbeat_task(mut led: Output<'static>) {
+pub async fn heartbeat_task(mut led: Output<'static>, period: Duration) {
     info!("Starting heartbeat task");
     loop {
-        // Blink LED every 500ms to show system is alive
         led.set_low(); // LED on (active low)
-        Timer::after(Duration::from_millis(100)).await;
+        Timer::after(HEARTBEAT_ON).await;
         led.set_high(); // LED off
-        Timer::after(Duration::from_millis(400)).await;
+        Timer::after(period - HEARTBEAT_ON).await;
     }
 }
 
@@ -47,16 +77,45 @@ pub async fn button_handler_task(button: Input<'static>, mut led: Output<'static
 }
 
 #[task]
-pub async fn led_pattern_task(mut led1: Output<'static>, mut led2: Output<'static>) {
+pub async fn led_pattern_task(
+    mut led1: Output<'static>,
+    mut led2: Output<'static>,
+    step: Duration,
+) {
     info!("Starting LED pattern task");
     loop {
         // Alternating pattern
         led1.set_low(); // LED1 on
         led2.set_high(); // LED2 off
-        Timer::after(Duration::from_millis(1000)).await;
+        Timer::after(step).await;
 
         led1.set_high(); // LED1 off
         led2.set_low(); // LED2 on
-        Timer::after(Duration::from_millis(1000)).await;
+        Timer::after(step).await;
     }
 }
+
+/// Configure the board LEDs and button 1, then spawn all three tasks
+///
+/// LED 1 is the heartbeat, LED 2 follows button 1 and LEDs 3/4 alternate.
+/// Buttons 2-4 are released unused.
+#[cfg(not(feature = "board-dongle"))]
+pub fn spawn_standard_tasks(
+    spawner: embassy_executor::Spawner,
+    leds: crate::board::Leds,
+    buttons: crate::board::Buttons,
+    timing: Timing,
+) -> Result<(), embassy_executor::SpawnError> {
+    use embassy_nrf::gpio::{Level, OutputDrive, Pull};
+
+    let led1 = Output::new(leds.led1, Level::High, OutputDrive::Standard);
+    let led2 = Output::new(leds.led2, Level::High, OutputDrive::Standard);
+    let led3 = Output::new(leds.led3, Level::High, OutputDrive::Standard);
+    let led4 = Output::new(leds.led4, Level::High, OutputDrive::Standard);
+    let button1 = Input::new(buttons.button1, Pull::Up);
+
+    spawner.spawn(heartbeat_task(led1, timing.heartbeat_period))?;
+    spawner.spawn(button_handler_task(button1, led2))?;
+    spawner.spawn(led_pattern_task(led3, led4, timing.pattern_step))?;
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! Machine-readable markers for hardware-in-the-loop scripts
+//!
+//! Host scripts driving a board through probe-rs or defmt-print look for
+//! log lines starting with `HIL ` and split the rest into `key=value`
+//! pairs, so they can decide pass/fail without scraping free-form text.
+//! Every line has `event` and `op`; the other keys depend on the event:
+//!
+//! | event | keys |
+//! |-------|------|
+//! | `start` | |
+//! | `end` | `result` (`pass`/`fail`), `us` |
+//! | `iter` | `n` (from 1) |
+//! | `summary` | `ok`, `failed` |
+//!
+//! Keys may be added to an event, but never renamed or removed.
+
+use defmt::info;
+use embassy_time::Duration;
+
+/// Prefix of every marker line
+pub const PREFIX: &str = "HIL";
+
+/// Outcome of an operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Outcome {
+    Pass,
+    Fail,
+}
+
+impl Outcome {
+    pub fn from_ok(ok: bool) -> Self {
+        if ok {
+            Outcome::Pass
+        } else {
+            Outcome::Fail
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Fail => "fail",
+        }
+    }
+}
+
+/// `op` has started
+pub fn start(op: &str) {
+    info!("{=str} event=start op={=str}", PREFIX, op);
+}
+
+/// `op` has finished after `elapsed`
+pub fn end(op: &str, outcome: Outcome, elapsed: Duration) {
+    info!(
+        "{=str} event=end op={=str} result={=str} us={=u64}",
+        PREFIX,
+        op,
+        outcome.as_str(),
+        elapsed.as_micros()
+    );
+}
+
+/// Iteration `n` (from 1) of a repeating `op` begins
+pub fn iteration(op: &str, n: u32) {
+    info!("{=str} event=iter op={=str} n={=u32}", PREFIX, op, n);
+}
+
+/// Final counts of a test `op` that checks many items
+pub fn summary(op: &str, ok: u32, failed: u32) {
+    info!(
+        "{=str} event=summary op={=str} ok={=u32} failed={=u32}",
+        PREFIX, op, ok, failed
+    );
+}
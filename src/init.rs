@@ -0,0 +1,98 @@
+//! Shared startup sequence for the binaries
+//!
+//! Every binary brings up Embassy the same way, and every BLE binary enables
+//! the SoftDevice with the same configuration. Keeping both here stops the
+//! copies from drifting apart.
+
+use embassy_nrf::interrupt::Priority;
+use embassy_nrf::Peripherals;
+
+#[cfg(feature = "ble")]
+use crate::{ble, state::APP_STATE, state::MAX_DEVICE_NAME_LEN, storage};
+#[cfg(feature = "ble")]
+use defmt::{info, unwrap};
+#[cfg(feature = "ble")]
+use embassy_executor::Spawner;
+#[cfg(feature = "ble")]
+use nrf_softdevice::{raw, Softdevice};
+
+/// Initialize Embassy with SoftDevice-compatible interrupt priorities
+///
+/// The SoftDevice reserves priorities 0, 1 and 4, so GPIOTE and the time
+/// driver run at P2. Binaries without BLE use the same settings so they
+/// behave identically whether or not the SoftDevice is flashed.
+pub fn init_embassy() -> Peripherals {
+    let mut config = embassy_nrf::config::Config::default();
+    config.gpiote_interrupt_priority = Priority::P2;
+    config.time_interrupt_priority = Priority::P2;
+    embassy_nrf::init(config)
+}
+
+/// Load settings saved by the CLI into the shared state
+#[cfg(feature = "ble")]
+pub fn restore_config() {
+    if let Some(saved) = storage::load_config() {
+        info!("Loaded saved config");
+        APP_STATE.update_config(|c| *c = saved);
+    }
+}
+
+/// Enable the SoftDevice and spawn its event task
+///
+/// The GAP device name is the one saved with `bt_name`, or `default_name`;
+/// a static address saved with `bt_addr` is applied once enabled. Call
+/// [`restore_config`] first so the saved address is known.
+#[cfg(feature = "ble")]
+pub fn init_softdevice(spawner: Spawner, default_name: &str) -> &'static Softdevice {
+    let device_name = storage::stored_device_name(default_name);
+
+    let config = nrf_softdevice::Config {
+        clock: Some(raw::nrf_clock_lf_cfg_t {
+            source: raw::NRF_CLOCK_LF_SRC_RC as u8,
+            rc_ctiv: 16,
+            rc_temp_ctiv: 2,
+            accuracy: raw::NRF_CLOCK_LF_ACCURACY_500_PPM as u8,
+        }),
+        conn_gap: Some(raw::ble_gap_conn_cfg_t {
+            conn_count: 6,
+            event_length: 6,
+        }),
+        conn_gatt: Some(raw::ble_gatt_conn_cfg_t { att_mtu: 256 }),
+        gatts_attr_tab_size: Some(raw::ble_gatts_cfg_attr_tab_size_t {
+            attr_tab_size: raw::BLE_GATTS_ATTR_TAB_SIZE_DEFAULT,
+        }),
+        gap_role_count: Some(raw::ble_gap_cfg_role_count_t {
+            adv_set_count: 1,
+            periph_role_count: 3,
+            central_role_count: 3,
+            central_sec_count: 0,
+            _bitfield_1: raw::ble_gap_cfg_role_count_t::new_bitfield_1(0),
+        }),
+        gap_device_name: Some(raw::ble_gap_cfg_device_name_t {
+            // The SoftDevice copies the name (VLOC_STACK), so a local is fine
+            p_value: device_name.as_ptr() as _,
+            current_len: device_name.len() as u16,
+            max_len: MAX_DEVICE_NAME_LEN as u16,
+            write_perm: unsafe { core::mem::zeroed() },
+            _bitfield_1: raw::ble_gap_cfg_device_name_t::new_bitfield_1(
+                raw::BLE_GATTS_VLOC_STACK as u8,
+            ),
+        }),
+        ..Default::default()
+    };
+
+    info!("Enabling SoftDevice...");
+    let sd = Softdevice::enable(&config);
+    if let Some(addr) = APP_STATE.config().ble_addr {
+        ble::set_static_address(sd, addr);
+    }
+    unwrap!(spawner.spawn(softdevice_task(sd)));
+    info!("✅ SoftDevice enabled and task spawned");
+    sd
+}
+
+#[cfg(feature = "ble")]
+#[embassy_executor::task]
+async fn softdevice_task(sd: &'static Softdevice) -> ! {
+    sd.run().await
+}
@@ -0,0 +1,40 @@
+//! Board LED identifiers
+//!
+//! [`LedId`] can only hold a valid LED number, so code indexing LED arrays
+//! with it never needs its own bounds checks.
+
+/// Number of user LEDs on the nRF52840-DK
+pub const LED_COUNT: usize = 4;
+
+/// LEDs that CLI commands may drive (LED1/LED2 indicate UART activity)
+pub const CLI_LEDS: [u8; 2] = [3, 4];
+
+/// A validated LED number (1-based, as printed on the board)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct LedId(u8);
+
+impl LedId {
+    /// Returns `None` unless `number` is in `1..=LED_COUNT`
+    pub const fn new(number: u8) -> Option<Self> {
+        if number >= 1 && number as usize <= LED_COUNT {
+            Some(Self(number))
+        } else {
+            None
+        }
+    }
+
+    /// LED number as printed on the board
+    pub const fn number(self) -> u8 {
+        self.0
+    }
+
+    /// Zero-based index for LED state arrays
+    pub const fn index(self) -> usize {
+        (self.0 - 1) as usize
+    }
+
+    /// Whether CLI commands are allowed to drive this LED
+    pub fn is_cli_controllable(self) -> bool {
+        CLI_LEDS.contains(&self.0)
+    }
+}
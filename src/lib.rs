@@ -1,13 +1,91 @@
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 //! nRF52840-DK Embassy Template Library
 //!
 //! This library provides reusable components for Embassy-based
 //! nRF52840 development including GPIO tasks and BLE functionality.
 
+// The fuzz build is host-only and leaves out every module that touches the
+// hardware; the firmware builds get the target crates through `hardware`
+#[cfg(not(any(feature = "hardware", feature = "fuzz")))]
+compile_error!("enable a firmware feature (default, gpio, ble, cli) or fuzz");
+#[cfg(all(feature = "hardware", feature = "fuzz"))]
+compile_error!("fuzz is a host build, use it with --no-default-features");
+
+// Per-board pin definitions
+#[cfg(not(feature = "fuzz"))]
+pub mod board;
+
+// Shared Embassy and SoftDevice startup
+#[cfg(not(feature = "fuzz"))]
+pub mod init;
+
 // pub mod ble_task;  // Disabled for GPIO-only mode
+// LED and button tasks shared by the GPIO apps
+#[cfg(not(feature = "fuzz"))]
 pub mod gpio_tasks;
 
+// Allocation-free number formatting
+#[cfg(not(feature = "fuzz"))]
+pub mod fmt_util;
+
+// Validated LED identifiers
+pub mod led;
+
+// BLE identity (device name and address)
+#[cfg(feature = "ble")]
+pub mod ble;
+
+// Persistent settings in the reserved flash region
+#[cfg(feature = "ble")]
+pub mod storage;
+
+// BLE download of the session log
+#[cfg(feature = "ble")]
+pub mod log_transfer;
+
+// Bit-bang UART receiver on any pin (only the decoder in fuzz builds)
+pub mod swuart;
+
+// Register dump of a GPIO pin
+#[cfg(not(feature = "fuzz"))]
+pub mod pinspect;
+
+// VDD monitoring and the low-supply flag
+#[cfg(not(feature = "fuzz"))]
+pub mod supply;
+
+// RAM layout and usage of the running image
+#[cfg(not(feature = "fuzz"))]
+pub mod mem_budget;
+
+// Periodic status report for the demo binaries
+#[cfg(not(feature = "fuzz"))]
+pub mod status;
+
+// Shared configuration, statistics and readings
+pub mod state;
+
+// Reset and shutdown helpers
+#[cfg(not(feature = "fuzz"))]
+pub mod system;
+
+// key=value defmt markers for hardware-in-the-loop scripts
+#[cfg(not(feature = "fuzz"))]
+pub mod hil;
+
+// Boot-relative and wall-clock time, and the defmt timestamp
+#[cfg(not(feature = "fuzz"))]
+pub mod clock;
+
+// Firmware version and build information from build.rs
+pub mod version;
+
+// Curated re-exports for downstream firmware
+#[cfg(not(feature = "fuzz"))]
+pub mod prelude;
+
 // CLI interface modules (conditional compilation for cli feature)
-#[cfg(feature = "cli")]
+// The fuzz feature builds only the hardware-independent parts for host fuzzing
+#[cfg(any(feature = "cli", feature = "fuzz"))]
 pub mod cli;
@@ -0,0 +1,146 @@
+//! BLE download of the flash session log
+//!
+//! A phone app connects, writes the byte offset it wants to the Offset
+//! characteristic, then reads Chunk. Every chunk is self-describing, so a
+//! transfer interrupted by a disconnect resumes by writing the next offset:
+//!
+//! | Bytes | Contents |
+//! |-------|----------|
+//! | 0-3   | Offset of the data (little endian) |
+//! | 4-7   | Total log size |
+//! | 8-11  | Sequence number of the oldest page; if it changes mid-transfer the ring moved and the download should restart |
+//! | 12-15 | CRC-32 of the data |
+//! | 16-   | Up to 228 bytes of raw log, pages oldest first |
+//!
+//! The raw log is the flash pages as stored (record layout in
+//! [`storage::read_log_raw`]); each record carries the uptime it was
+//! logged at.
+//!
+//! The Status characteristic holds flag bits, refreshed on connect and with
+//! every chunk: bit 0 means the supply is low and the client should finish
+//! soon. While the supply is low the service stops advertising, as the
+//! other non-essential activity does.
+
+use crate::storage;
+use crate::supply;
+use defmt::{info, warn};
+use embassy_time::{Duration, Timer};
+use heapless::Vec;
+use nrf_softdevice::ble::{gatt_server, peripheral};
+use nrf_softdevice::Softdevice;
+
+/// Longest Chunk value; fits one read with the configured 256-byte ATT MTU
+const CHUNK_LEN: usize = 244;
+const CHUNK_HEADER_LEN: usize = 16;
+
+/// Status bit: the supply is low
+const STATUS_LOW_SUPPLY: u8 = 0x01;
+
+/// How long each advertising round lasts before the supply is checked again
+const ADV_ROUND_10MS: u16 = 3000;
+/// How often a low supply is re-checked before advertising resumes
+const LOW_SUPPLY_POLL: Duration = Duration::from_secs(10);
+
+/// Flags (LE general discoverable, no BR/EDR) and the service UUID
+#[rustfmt::skip]
+static ADV_DATA: [u8; 21] = [
+    0x02, 0x01, 0x06,
+    0x11, 0x07,
+    0x38, 0xcf, 0x62, 0x0a, 0xc3, 0xfb, 0x10, 0x9f,
+    0xeb, 0x11, 0x54, 0x23, 0xe0, 0x12, 0x73, 0x9e,
+];
+
+#[nrf_softdevice::gatt_service(uuid = "9e7312e0-2354-11eb-9f10-fbc30a62cf38")]
+pub struct LogService {
+    #[characteristic(uuid = "9e7312e1-2354-11eb-9f10-fbc30a62cf38", write)]
+    offset: u32,
+    #[characteristic(uuid = "9e7312e2-2354-11eb-9f10-fbc30a62cf38", read)]
+    chunk: Vec<u8, CHUNK_LEN>,
+    #[characteristic(uuid = "9e7312e3-2354-11eb-9f10-fbc30a62cf38", read)]
+    status: u8,
+}
+
+#[nrf_softdevice::gatt_server]
+pub struct Server {
+    log: LogService,
+}
+
+/// Chunk value for the data starting at `offset`
+fn build_chunk(offset: u32) -> Vec<u8, CHUNK_LEN> {
+    let mut data = [0u8; CHUNK_LEN - CHUNK_HEADER_LEN];
+    let len = storage::read_log_raw(offset, &mut data);
+
+    let mut chunk = Vec::new();
+    let _ = chunk.extend_from_slice(&offset.to_le_bytes());
+    let _ = chunk.extend_from_slice(&storage::LOG_SIZE.to_le_bytes());
+    let _ = chunk.extend_from_slice(&storage::oldest_log_seq().to_le_bytes());
+    let _ = chunk.extend_from_slice(&storage::crc32(&data[..len]).to_le_bytes());
+    let _ = chunk.extend_from_slice(&data[..len]);
+    chunk
+}
+
+/// Status characteristic value
+fn status() -> u8 {
+    if supply::low_supply() {
+        STATUS_LOW_SUPPLY
+    } else {
+        0
+    }
+}
+
+/// Advertise the log service and serve one client at a time, forever
+#[embassy_executor::task]
+pub async fn transfer_task(sd: &'static Softdevice) -> ! {
+    let server = match Server::new(sd) {
+        Ok(server) => server,
+        Err(e) => {
+            warn!("Log transfer: service registration failed: {:?}", e);
+            loop {
+                Timer::after(Duration::from_secs(3600)).await;
+            }
+        }
+    };
+
+    // Advertise in rounds so a supply that drops meanwhile stops it
+    let config = peripheral::Config {
+        timeout: Some(ADV_ROUND_10MS),
+        ..Default::default()
+    };
+    loop {
+        if supply::low_supply() {
+            info!("Log transfer: supply low, advertising paused");
+            while supply::low_supply() {
+                Timer::after(LOW_SUPPLY_POLL).await;
+            }
+            info!("Log transfer: supply recovered, advertising resumed");
+        }
+
+        let adv = peripheral::ConnectableAdvertisement::ScannableUndirected {
+            adv_data: &ADV_DATA,
+            scan_data: &[],
+        };
+        let conn = match peripheral::advertise_connectable(sd, adv, &config).await {
+            Ok(conn) => conn,
+            Err(peripheral::AdvertiseError::Timeout) => continue,
+            Err(e) => {
+                warn!("Log transfer: advertising failed: {:?}", e);
+                Timer::after(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        info!("Log transfer: client connected");
+
+        let _ = server.log.chunk_set(&build_chunk(0));
+        let _ = server.log.status_set(&status());
+        let reason = gatt_server::run(&conn, &server, |event| match event {
+            ServerEvent::Log(LogServiceEvent::OffsetWrite(offset)) => {
+                if server.log.chunk_set(&build_chunk(offset)).is_err() {
+                    warn!("Log transfer: chunk update failed");
+                }
+                let _ = server.log.status_set(&status());
+            }
+        })
+        .await;
+        info!("Log transfer: client disconnected: {:?}", reason);
+    }
+}
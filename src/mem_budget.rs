@@ -0,0 +1,70 @@
+//! RAM budget of the running image
+//!
+//! build.rs exports the RAM region of the selected memory layout; the linker
+//! symbols from cortex-m-rt give how much of it the statics use. Whatever is
+//! left between the end of the statics and the stack pointer is headroom for
+//! the stack, which is what runs out first when subsystems are added.
+
+use core::ptr::addr_of;
+
+/// Start of the nRF52840 RAM, where the SoftDevice's reservation begins
+const RAM_START: u32 = 0x2000_0000;
+
+/// Start of the application RAM region in the memory layout
+pub const RAM_ORIGIN: u32 = parse_u32(env!("BUILD_RAM_ORIGIN"));
+/// Size of the application RAM region in the memory layout
+pub const RAM_LENGTH: u32 = parse_u32(env!("BUILD_RAM_LENGTH"));
+
+/// Headroom below which the budget is reported as tight
+pub const LOW_HEADROOM: u32 = 8 * 1024;
+
+extern "C" {
+    /// End of .data, .bss and .uninit (cortex-m-rt)
+    static __sheap: u8;
+}
+
+/// Decimal digits to a number at compile time, for values from the build environment
+pub(crate) const fn parse_u32(s: &str) -> u32 {
+    let bytes = s.as_bytes();
+    let mut value = 0u32;
+    let mut i = 0;
+    while i < bytes.len() {
+        value = value * 10 + (bytes[i] - b'0') as u32;
+        i += 1;
+    }
+    value
+}
+
+/// Where the RAM of this image goes, in bytes
+#[derive(Debug, Clone, Copy, defmt::Format)]
+pub struct RamBudget {
+    /// Below the layout's RAM origin, reserved for the SoftDevice (0 without one)
+    pub softdevice: u32,
+    /// All statics: .data, .bss and .uninit
+    pub statics: u32,
+    /// Stack in use at the time of the call
+    pub stack_used: u32,
+    /// Between the statics and the stack pointer
+    pub headroom: u32,
+}
+
+impl RamBudget {
+    /// Whether the headroom is below [`LOW_HEADROOM`]
+    pub fn is_tight(&self) -> bool {
+        self.headroom < LOW_HEADROOM
+    }
+}
+
+/// Measure the budget from the linker symbols and the current stack pointer
+pub fn ram_budget() -> RamBudget {
+    // Only the address of the symbol is used, never its contents
+    let statics_end = unsafe { addr_of!(__sheap) } as u32;
+    let stack_top = RAM_ORIGIN + RAM_LENGTH;
+    let sp = cortex_m::register::msp::read();
+    RamBudget {
+        softdevice: RAM_ORIGIN - RAM_START,
+        statics: statics_end - RAM_ORIGIN,
+        stack_used: stack_top.saturating_sub(sp),
+        headroom: sp.saturating_sub(statics_end),
+    }
+}
@@ -0,0 +1,123 @@
+//! Register-level view of one GPIO pin
+//!
+//! Reads the pin's PIN_CNF, its bit of the IN and OUT latches and every
+//! GPIOTE channel that selects it, for diagnosing wiring and drive problems
+//! (a missing pull, a pin left as input) without a debugger. Registers are
+//! only read, so a pin owned by a driver can be inspected safely.
+
+use embassy_nrf::pac;
+use heapless::Vec;
+
+/// GPIOTE channels on the nRF52840
+pub const GPIOTE_CHANNELS: usize = 8;
+
+/// Raw PIN_CNF value with its fields decoded by name
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct PinCnf(pub u32);
+
+impl PinCnf {
+    pub fn is_output(self) -> bool {
+        self.0 & 0x1 != 0
+    }
+
+    /// Whether the input buffer is connected, needed to read IN
+    pub fn input_connected(self) -> bool {
+        self.0 & 0x2 == 0
+    }
+
+    pub fn pull(self) -> &'static str {
+        match (self.0 >> 2) & 0x3 {
+            0 => "none",
+            1 => "down",
+            3 => "up",
+            _ => "reserved",
+        }
+    }
+
+    /// Drive strength for 0 and 1: S standard, H high, D disconnected
+    pub fn drive(self) -> &'static str {
+        const DRIVES: [&str; 8] = [
+            "S0S1", "H0S1", "S0H1", "H0H1", "D0S1", "D0H1", "S0D1", "H0D1",
+        ];
+        DRIVES[((self.0 >> 8) & 0x7) as usize]
+    }
+
+    /// Level that raises DETECT (wake from System OFF)
+    pub fn sense(self) -> &'static str {
+        match (self.0 >> 16) & 0x3 {
+            0 => "off",
+            2 => "high",
+            3 => "low",
+            _ => "reserved",
+        }
+    }
+}
+
+/// A GPIOTE channel configured for the inspected pin
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct GpioteChannel {
+    pub index: u8,
+    /// Raw CONFIG register
+    pub config: u32,
+}
+
+impl GpioteChannel {
+    pub fn mode(&self) -> &'static str {
+        match self.config & 0x3 {
+            1 => "event",
+            3 => "task",
+            _ => "disabled",
+        }
+    }
+
+    pub fn polarity(&self) -> &'static str {
+        match (self.config >> 16) & 0x3 {
+            0 => "none",
+            1 => "rise",
+            2 => "fall",
+            _ => "toggle",
+        }
+    }
+
+    /// Initial output level in task mode
+    pub fn outinit_high(&self) -> bool {
+        self.config & (1 << 20) != 0
+    }
+}
+
+/// Snapshot of one pin's registers
+#[derive(Debug, Clone, PartialEq, defmt::Format)]
+pub struct PinState {
+    pub cnf: PinCnf,
+    /// IN latch; only meaningful while the input buffer is connected
+    pub input: bool,
+    /// OUT latch, driven onto the pin only in output mode
+    pub output: bool,
+    pub gpiote: Vec<GpioteChannel, GPIOTE_CHANNELS>,
+}
+
+/// Read the registers of `pin` (port * 32 + pin number)
+pub fn inspect(pin: u8) -> PinState {
+    let port = if pin < 32 { pac::P0 } else { pac::P1 };
+    let bit = (pin % 32) as usize;
+
+    let mut gpiote = Vec::new();
+    for index in 0..GPIOTE_CHANNELS {
+        let config = pac::GPIOTE.config(index).read().0;
+        // PSEL is bits 8-12, PORT is bit 13
+        let selected = ((config >> 8) & 0x3f) as u8;
+        if config & 0x3 != 0 && selected == pin {
+            let _ = gpiote.push(GpioteChannel {
+                index: index as u8,
+                config,
+            });
+        }
+    }
+
+    PinState {
+        cnf: PinCnf(port.pin_cnf(bit).read().0),
+        input: port.in_().read().0 & (1 << bit) != 0,
+        output: port.out().read().0 & (1 << bit) != 0,
+        gpiote,
+    }
+}
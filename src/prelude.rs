@@ -0,0 +1,24 @@
+//! Stable public API
+//!
+//! `use nrf52840_dk_template::prelude::*;` brings in what the bundled binaries
+//! are built from. Names re-exported here are kept across minor versions;
+//! other module paths may move as the crate grows.
+
+pub use crate::board::{self, Leds};
+#[cfg(not(feature = "board-dongle"))]
+pub use crate::board::{Buttons, ConsoleUart};
+pub use crate::board_leds;
+#[cfg(not(feature = "board-dongle"))]
+pub use crate::{board_buttons, board_console_uart};
+
+pub use crate::init;
+pub use crate::led::LedId;
+pub use crate::state::{Config, APP_STATE};
+pub use crate::system::{self, BootMode};
+pub use crate::version;
+
+#[cfg(feature = "ble")]
+pub use crate::storage::{Counter, Storage};
+
+#[cfg(feature = "cli")]
+pub use crate::cli::{session, CommandHandler, Terminal, Transport, UsbSerial};
@@ -0,0 +1,253 @@
+//! Shared application state
+//!
+//! A single static [`APP_STATE`] holds the runtime configuration, statistics
+//! and most recent readings. The CLI, BLE and storage code all read and update
+//! this one copy instead of keeping their own snapshots.
+
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use heapless::String;
+
+/// Longest GAP device name that can be configured
+pub const MAX_DEVICE_NAME_LEN: usize = 31;
+/// Longest site name, meter serial or installer note
+pub const MAX_META_LEN: usize = 64;
+/// Longest BLE scan in seconds
+pub const MAX_SCAN_TIME_SECS: u16 = 60;
+/// Lowest threshold accepted by `supply low`, the nRF52840 minimum VDD
+pub const MIN_LOW_SUPPLY_MV: u16 = 1700;
+/// Highest threshold accepted by `supply low`, the nRF52840 maximum VDD
+pub const MAX_LOW_SUPPLY_MV: u16 = 3600;
+
+/// Scanner duty cycle, both values in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct ScanTiming {
+    pub interval_ms: u16,
+    pub window_ms: u16,
+}
+
+impl ScanTiming {
+    /// Shortest interval/window the SoftDevice accepts (rounded up from 2.5 ms)
+    pub const MIN_MS: u16 = 3;
+    /// Longest interval/window the SoftDevice accepts
+    pub const MAX_MS: u16 = 10240;
+
+    /// Validated timing: both values in range and the window no longer than the interval
+    pub const fn new(interval_ms: u16, window_ms: u16) -> Option<Self> {
+        if interval_ms < Self::MIN_MS
+            || interval_ms > Self::MAX_MS
+            || window_ms < Self::MIN_MS
+            || window_ms > interval_ms
+        {
+            return None;
+        }
+        Some(Self {
+            interval_ms,
+            window_ms,
+        })
+    }
+}
+
+/// What the session log does once it reaches its limit
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum LogMode {
+    /// Erase the oldest page of records to make room
+    Overwrite,
+    /// Keep what is stored and drop new records
+    StopWhenFull,
+}
+
+/// Retention policy for the flash session log
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct LogPolicy {
+    pub mode: LogMode,
+    /// Records kept before the mode applies, 0 for as many as fit
+    pub max_entries: u16,
+}
+
+impl LogPolicy {
+    pub const fn new() -> Self {
+        Self {
+            mode: LogMode::Overwrite,
+            max_entries: 0,
+        }
+    }
+}
+
+/// Runtime configuration
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Scan time used by `bt_scan` when no time is given (seconds)
+    pub scan_time_secs: u16,
+    /// Use ANSI colors in CLI output
+    pub color: bool,
+    /// Print a bare `> ` prompt instead of the device name
+    pub compact_prompt: bool,
+    /// GAP device name, empty to use the binary's built-in name
+    pub device_name: String<MAX_DEVICE_NAME_LEN>,
+    /// Scanner interval/window, `None` for the SoftDevice defaults
+    pub scan_timing: Option<ScanTiming>,
+    /// Static random BLE address (LSB first), `None` for the factory address
+    pub ble_addr: Option<[u8; 6]>,
+    /// Append console commands and responses to the flash session log
+    pub session_log: bool,
+    /// Where this bridge is installed, empty if not set
+    pub site: String<MAX_META_LEN>,
+    /// Serial number of the attached meter, empty if not set
+    pub meter_serial: String<MAX_META_LEN>,
+    /// Free-form installer note, empty if not set
+    pub note: String<MAX_META_LEN>,
+    /// VDD below which the supply is flagged low (mV), 0 to disable
+    pub low_supply_mv: u16,
+    /// Session log retention
+    pub log_policy: LogPolicy,
+}
+
+impl Config {
+    pub const fn new() -> Self {
+        Self {
+            scan_time_secs: 10,
+            color: true,
+            compact_prompt: false,
+            device_name: String::new(),
+            scan_timing: None,
+            ble_addr: None,
+            session_log: false,
+            site: String::new(),
+            meter_serial: String::new(),
+            note: String::new(),
+            low_supply_mv: 0,
+            log_policy: LogPolicy::new(),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Counters accumulated since boot
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    pub commands_executed: u32,
+    pub command_errors: u32,
+    pub unknown_commands: u32,
+    pub ble_scans: u32,
+    pub console: ConsoleErrors,
+}
+
+impl Stats {
+    pub const fn new() -> Self {
+        Self {
+            commands_executed: 0,
+            command_errors: 0,
+            unknown_commands: 0,
+            ble_scans: 0,
+            console: ConsoleErrors::new(),
+        }
+    }
+}
+
+/// Console receive errors by kind, and how often the UART was re-initialized
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleErrors {
+    pub framing: u32,
+    pub parity: u32,
+    pub overrun: u32,
+    pub breaks: u32,
+    pub other: u32,
+    pub reinits: u32,
+}
+
+impl ConsoleErrors {
+    pub const fn new() -> Self {
+        Self {
+            framing: 0,
+            parity: 0,
+            overrun: 0,
+            breaks: 0,
+            other: 0,
+            reinits: 0,
+        }
+    }
+
+    /// All receive errors regardless of kind
+    pub fn total(&self) -> u32 {
+        self.framing + self.parity + self.overrun + self.breaks + self.other
+    }
+}
+
+/// Most recent sensor and scan results
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Readings {
+    /// Last die temperature in °C
+    pub temperature: Option<f32>,
+    /// Number of unique devices found by the last BLE scan
+    pub scan_devices: Option<u16>,
+    /// Last supply voltage in mV
+    pub vdd_mv: Option<u16>,
+    /// Supply is below the configured threshold
+    pub low_supply: bool,
+}
+
+impl Readings {
+    pub const fn new() -> Self {
+        Self {
+            temperature: None,
+            scan_devices: None,
+            vdd_mv: None,
+            low_supply: false,
+        }
+    }
+}
+
+pub struct AppState {
+    config: Mutex<CriticalSectionRawMutex, RefCell<Config>>,
+    stats: Mutex<CriticalSectionRawMutex, RefCell<Stats>>,
+    readings: Mutex<CriticalSectionRawMutex, RefCell<Readings>>,
+}
+
+impl AppState {
+    pub const fn new() -> Self {
+        Self {
+            config: Mutex::new(RefCell::new(Config::new())),
+            stats: Mutex::new(RefCell::new(Stats::new())),
+            readings: Mutex::new(RefCell::new(Readings::new())),
+        }
+    }
+
+    pub fn config(&self) -> Config {
+        self.config.lock(|c| c.borrow().clone())
+    }
+
+    pub fn update_config(&self, f: impl FnOnce(&mut Config)) {
+        self.config.lock(|c| f(&mut c.borrow_mut()));
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats.lock(|s| *s.borrow())
+    }
+
+    pub fn update_stats(&self, f: impl FnOnce(&mut Stats)) {
+        self.stats.lock(|s| f(&mut s.borrow_mut()));
+    }
+
+    pub fn readings(&self) -> Readings {
+        self.readings.lock(|r| *r.borrow())
+    }
+
+    pub fn update_readings(&self, f: impl FnOnce(&mut Readings)) {
+        self.readings.lock(|r| f(&mut r.borrow_mut()));
+    }
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The one application state instance shared by every subsystem
+pub static APP_STATE: AppState = AppState::new();
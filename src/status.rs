@@ -0,0 +1,63 @@
+//! Periodic status report for the demo binaries
+//!
+//! Each report logs what changed since the previous one (commands, errors,
+//! BLE scans, console line errors) along with the uptime and health flags,
+//! instead of a fixed "still running" line. The interval is set at build
+//! time with `STATUS_INTERVAL_SECS` (default 10, `0` turns reports off).
+
+use crate::clock;
+use crate::mem_budget;
+use crate::state::{Stats, APP_STATE};
+use crate::supply;
+use defmt::{info, warn};
+use embassy_executor::task;
+use embassy_time::{Duration, Timer};
+
+/// Report interval from the build environment, `None` when reports are off
+pub const INTERVAL: Option<Duration> = match option_env!("STATUS_INTERVAL_SECS") {
+    None => Some(Duration::from_secs(10)),
+    Some(secs) => match mem_budget::parse_u32(secs) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs as u64)),
+    },
+};
+
+/// Log a report every `interval`
+#[task]
+pub async fn status_task(interval: Duration) {
+    let mut last = APP_STATE.stats();
+    loop {
+        Timer::after(interval).await;
+        let stats = APP_STATE.stats();
+        report(&stats, &last);
+        last = stats;
+    }
+}
+
+fn report(stats: &Stats, last: &Stats) {
+    let budget = mem_budget::ram_budget();
+    info!(
+        "Status: up {}s, +{} commands (+{} errors), +{} scans, +{} line errors, headroom {} B",
+        clock::uptime().as_secs(),
+        stats.commands_executed - last.commands_executed,
+        (stats.command_errors + stats.unknown_commands)
+            - (last.command_errors + last.unknown_commands),
+        stats.ble_scans - last.ble_scans,
+        stats.console.total() - last.console.total(),
+        budget.headroom
+    );
+    if supply::low_supply() {
+        warn!("Status: supply low");
+    }
+    if budget.is_tight() {
+        warn!("Status: RAM headroom below {} B", mem_budget::LOW_HEADROOM);
+    }
+}
+
+/// Spawn [`status_task`] unless reports are turned off
+pub fn spawn(spawner: embassy_executor::Spawner) -> Result<(), embassy_executor::SpawnError> {
+    match INTERVAL {
+        Some(interval) => spawner.spawn(status_task(interval)),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,977 @@
+//! Persistent configuration storage
+//!
+//! The last 32 KiB of flash (excluded from the SoftDevice memory layout) hold
+//! persisted settings and the console session log. Reads go straight to the
+//! memory-mapped flash so the config can be loaded before the SoftDevice is
+//! enabled; writes go through the SoftDevice flash API, which owns the NVMC
+//! once BLE is running.
+//!
+//! | Page | Contents |
+//! |------|----------|
+//! | 0    | Config slot A |
+//! | 1-4  | Session log ring |
+//! | 5    | Config slot B |
+//! | 6-7  | Lifetime counters (alternating) |
+//!
+//! Writes are laid out so a power loss at any point loses at most the write
+//! in progress. The config alternates between two slots, each sealed with a
+//! sequence number and CRC, and the newest intact slot wins at load. Session
+//! log records carry their own checksum, so a torn record is skipped.
+//!
+//! A session log record is its length, direction tag and checksum, then the
+//! payload, padded to 4 bytes. Records tagged `I` (input) or `O` (output)
+//! start their payload with the uptime in microseconds (8 bytes, little
+//! endian), the same time base as the defmt log; older `>` and `<` records
+//! hold only the text.
+
+use crate::clock;
+use crate::state::{
+    Config, LogMode, LogPolicy, MAX_DEVICE_NAME_LEN, MAX_LOW_SUPPLY_MV, MAX_META_LEN,
+    MAX_SCAN_TIME_SECS, MIN_LOW_SUPPLY_MV,
+};
+use defmt::{info, warn};
+use embassy_time::Duration;
+use embedded_storage_async::nor_flash::NorFlash;
+use heapless::String;
+use nrf_softdevice::{Flash, Softdevice};
+
+/// Start of the reserved storage region (must match memory-softdevice.x)
+#[cfg(not(feature = "board-dongle"))]
+pub const STORAGE_START: u32 = 0x000F_8000;
+/// Start of the reserved storage region, below the Dongle's bootloader
+/// (must match memory-dongle.x)
+#[cfg(feature = "board-dongle")]
+pub const STORAGE_START: u32 = 0x000D_8000;
+/// Size of the reserved storage region
+pub const STORAGE_SIZE: u32 = 32 * 1024;
+/// nRF52840 flash page size
+pub const PAGE_SIZE: u32 = 4096;
+
+/// Pages holding alternate copies of the config record
+const CONFIG_SLOTS: [u32; 2] = [STORAGE_START, STORAGE_START + 5 * PAGE_SIZE];
+/// Room for the record in a slot; fixed so the trailer doesn't move when
+/// fields are appended to the record
+const SLOT_RECORD_SPACE: usize = 504;
+/// Record space, then the sequence number and the CRC-32 of both
+const SLOT_LEN: usize = SLOT_RECORD_SPACE + 8;
+
+/// "CFG" followed by the layout version digit
+const CONFIG_MAGIC: &[u8; 3] = b"CFG";
+/// Bump when fields are appended; older records load with later fields at defaults.
+/// Version 5 adds no fields; it marks records from firmware that rejects
+/// unsealed slots
+const CONFIG_VERSION: u8 = b'5';
+/// Records of this version and later were only ever written sealed, so an
+/// unsealed one is a write torn before its trailer
+const FIRST_SEALED_VERSION: u8 = b'4';
+const CONFIG_RECORD_LEN: usize = 252;
+const NAME_OFFSET: usize = 16;
+/// Site, meter serial and note lengths, then the three fields back to back (version 2)
+const META_LEN_OFFSET: usize = 48;
+const META_OFFSET: usize = 52;
+/// Low-supply threshold in mV (version 3)
+const LOW_SUPPLY_OFFSET: usize = 244;
+/// Session log mode byte, a spare byte, then the entry limit (version 4)
+const LOG_POLICY_OFFSET: usize = 248;
+
+/// Bits of the config record's flags byte
+const FLAG_SESSION_LOG: u8 = 0x01;
+const FLAG_COMPACT_PROMPT: u8 = 0x02;
+
+/// First page of the session log ring
+const LOG_START: u32 = STORAGE_START + PAGE_SIZE;
+/// Pages in the session log ring; the oldest is erased when all are full
+const LOG_PAGES: u32 = 4;
+/// Each log page starts with its sequence number, erased (0xFFFF_FFFF) if unused
+const LOG_HEADER_LEN: u32 = 4;
+/// Longest logged line; longer lines are truncated
+pub const MAX_LOG_LINE: usize = 240;
+/// Length byte of erased flash, marking the end of a page's records
+const LOG_END: u8 = 0xFF;
+/// Length, direction and checksum bytes before a record's payload
+const LOG_RECORD_HEADER_LEN: usize = 4;
+/// Uptime at the start of a timestamped record's payload
+const LOG_TIMESTAMP_LEN: usize = 8;
+
+/// Pages that take turns holding the lifetime counters
+const COUNTER_PAGES: [u32; 2] = [STORAGE_START + 6 * PAGE_SIZE, STORAGE_START + 7 * PAGE_SIZE];
+/// Sequence number and "CNT1", written last when a counter page is started
+const COUNTER_HEADER_LEN: u32 = 8;
+const COUNTER_MAGIC: &[u8; 4] = b"CNT1";
+/// Value, then the counter id and its complement; the id word is written
+/// second so a torn cell reads as erased
+const COUNTER_CELL_LEN: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum StorageError {
+    /// The SoftDevice rejected the erase or write
+    Flash,
+}
+
+/// What a config slot holds
+#[derive(Debug, Clone)]
+enum Slot {
+    Erased,
+    /// A record in slot A saved before slots were sealed, treated as sequence 0
+    Unsealed(Config),
+    Sealed(u32, Config),
+    /// Torn write or bit rot
+    Corrupt,
+}
+
+impl Slot {
+    fn read(index: usize) -> Self {
+        let bytes = flash_bytes(CONFIG_SLOTS[index], SLOT_LEN);
+        if bytes.iter().all(|&b| b == 0xFF) {
+            return Slot::Erased;
+        }
+        let (record, trailer) = bytes.split_at(SLOT_RECORD_SPACE);
+        if trailer.iter().all(|&b| b == 0xFF) {
+            // Firmware before sealing only ever wrote slot A
+            let legacy = index == 0 && record[3] < FIRST_SEALED_VERSION;
+            return match decode_config(record) {
+                Some(config) if legacy => Slot::Unsealed(config),
+                _ => Slot::Corrupt,
+            };
+        }
+        let seq = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let crc = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        if crc != crc32(&bytes[..SLOT_RECORD_SPACE + 4]) {
+            return Slot::Corrupt;
+        }
+        decode_config(record).map_or(Slot::Corrupt, |config| Slot::Sealed(seq, config))
+    }
+
+    /// Sequence number for picking the newest slot, `None` if unusable
+    fn seq(&self) -> Option<u32> {
+        match self {
+            Slot::Unsealed(_) => Some(0),
+            Slot::Sealed(seq, _) => Some(*seq),
+            Slot::Erased | Slot::Corrupt => None,
+        }
+    }
+}
+
+/// Index of the slot holding the newest usable config
+fn newest_slot(slots: &[Slot; 2]) -> Option<usize> {
+    match (slots[0].seq(), slots[1].seq()) {
+        (Some(a), Some(b)) => Some(if b > a { 1 } else { 0 }),
+        (Some(_), None) => Some(0),
+        (None, Some(_)) => Some(1),
+        (None, None) => None,
+    }
+}
+
+/// Load the persisted config, or `None` if nothing valid is stored
+pub(crate) fn load_config() -> Option<Config> {
+    let mut slots = [Slot::read(0), Slot::read(1)];
+    let newest = newest_slot(&slots)?;
+    match core::mem::replace(&mut slots[newest], Slot::Erased) {
+        Slot::Unsealed(config) | Slot::Sealed(_, config) => Some(config),
+        Slot::Erased | Slot::Corrupt => None,
+    }
+}
+
+/// Device name from the persisted config, or `default` if none was set
+pub(crate) fn stored_device_name(default: &str) -> String<MAX_DEVICE_NAME_LEN> {
+    let mut name = load_config()
+        .map(|config| config.device_name)
+        .unwrap_or_default();
+    if name.is_empty() {
+        let _ = name.push_str(default);
+    }
+    name
+}
+
+fn encode_config(config: &Config) -> [u8; CONFIG_RECORD_LEN] {
+    let mut record = [0u8; CONFIG_RECORD_LEN];
+    record[0..3].copy_from_slice(CONFIG_MAGIC);
+    record[3] = CONFIG_VERSION;
+    record[4..6].copy_from_slice(&config.scan_time_secs.to_le_bytes());
+    record[6] = config.color as u8;
+    record[7] = config.device_name.len() as u8;
+    if let Some(addr) = config.ble_addr {
+        record[8] = 1;
+        record[10..16].copy_from_slice(&addr);
+    }
+    if config.session_log {
+        record[9] |= FLAG_SESSION_LOG;
+    }
+    if config.compact_prompt {
+        record[9] |= FLAG_COMPACT_PROMPT;
+    }
+    record[NAME_OFFSET..NAME_OFFSET + config.device_name.len()]
+        .copy_from_slice(config.device_name.as_bytes());
+    let meta = [&config.site, &config.meter_serial, &config.note];
+    for (i, field) in meta.iter().enumerate() {
+        let offset = META_OFFSET + i * MAX_META_LEN;
+        record[META_LEN_OFFSET + i] = field.len() as u8;
+        record[offset..offset + field.len()].copy_from_slice(field.as_bytes());
+    }
+    record[LOW_SUPPLY_OFFSET..LOW_SUPPLY_OFFSET + 2]
+        .copy_from_slice(&config.low_supply_mv.to_le_bytes());
+    record[LOG_POLICY_OFFSET] = match config.log_policy.mode {
+        LogMode::Overwrite => 0,
+        LogMode::StopWhenFull => 1,
+    };
+    record[LOG_POLICY_OFFSET + 2..LOG_POLICY_OFFSET + 4]
+        .copy_from_slice(&config.log_policy.max_entries.to_le_bytes());
+    record
+}
+
+fn decode_config(record: &[u8]) -> Option<Config> {
+    if record.len() < CONFIG_RECORD_LEN || &record[0..3] != CONFIG_MAGIC {
+        return None;
+    }
+    let version = record[3];
+    if !(b'1'..=CONFIG_VERSION).contains(&version) {
+        return None;
+    }
+
+    let mut config = Config::new();
+    config.scan_time_secs = u16::from_le_bytes([record[4], record[5]]);
+    if !(1..=MAX_SCAN_TIME_SECS).contains(&config.scan_time_secs) {
+        return None;
+    }
+    config.color = record[6] != 0;
+
+    let name_len = record[7] as usize;
+    if name_len > MAX_DEVICE_NAME_LEN {
+        return None;
+    }
+    let name = core::str::from_utf8(&record[NAME_OFFSET..NAME_OFFSET + name_len]).ok()?;
+    let _ = config.device_name.push_str(name);
+
+    if record[8] == 1 {
+        let mut addr = [0u8; 6];
+        addr.copy_from_slice(&record[10..16]);
+        config.ble_addr = Some(addr);
+    }
+    config.session_log = record[9] & FLAG_SESSION_LOG != 0;
+    config.compact_prompt = record[9] & FLAG_COMPACT_PROMPT != 0;
+
+    if version >= b'2' {
+        let meta = [&mut config.site, &mut config.meter_serial, &mut config.note];
+        for (i, field) in meta.into_iter().enumerate() {
+            let len = record[META_LEN_OFFSET + i] as usize;
+            let offset = META_OFFSET + i * MAX_META_LEN;
+            if len > MAX_META_LEN {
+                return None;
+            }
+            let _ = field.push_str(core::str::from_utf8(&record[offset..offset + len]).ok()?);
+        }
+    }
+
+    if version >= b'3' {
+        config.low_supply_mv =
+            u16::from_le_bytes([record[LOW_SUPPLY_OFFSET], record[LOW_SUPPLY_OFFSET + 1]]);
+        let threshold = MIN_LOW_SUPPLY_MV..=MAX_LOW_SUPPLY_MV;
+        if config.low_supply_mv != 0 && !threshold.contains(&config.low_supply_mv) {
+            return None;
+        }
+    }
+
+    if version >= b'4' {
+        config.log_policy = LogPolicy {
+            mode: match record[LOG_POLICY_OFFSET] {
+                1 => LogMode::StopWhenFull,
+                _ => LogMode::Overwrite,
+            },
+            max_entries: u16::from_le_bytes([
+                record[LOG_POLICY_OFFSET + 2],
+                record[LOG_POLICY_OFFSET + 3],
+            ]),
+        };
+    }
+
+    Some(config)
+}
+
+/// CRC-32 (IEEE 802.3), bitwise to avoid a 1 KiB table
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(!0, data)
+}
+
+/// Feed `data` into a running CRC-32 that started at `!0`
+pub(crate) fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// Checksum of a session log record: the low half of the CRC-32 of its
+/// length, direction and payload
+fn log_checksum(len: u8, tag: u8, payload: &[u8]) -> [u8; 2] {
+    let crc = !crc32_update(crc32_update(!0, &[len, tag]), payload);
+    (crc as u16).to_le_bytes()
+}
+
+/// Which side of the console a session log line came from
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum LogDirection {
+    /// A command line typed by the user
+    Input,
+    /// A response written by the firmware
+    Output,
+}
+
+impl LogDirection {
+    /// Tag of a timestamped record
+    fn tag(self) -> u8 {
+        match self {
+            LogDirection::Input => b'I',
+            LogDirection::Output => b'O',
+        }
+    }
+
+    /// Direction of a record and whether it carries a timestamp
+    fn from_tag(tag: u8) -> Option<(Self, bool)> {
+        match tag {
+            b'I' => Some((LogDirection::Input, true)),
+            b'O' => Some((LogDirection::Output, true)),
+            // Written before records were timestamped
+            b'>' => Some((LogDirection::Input, false)),
+            b'<' => Some((LogDirection::Output, false)),
+            _ => None,
+        }
+    }
+}
+
+/// One session log line
+#[derive(Debug, Clone, Copy)]
+pub struct LogEntry {
+    pub direction: LogDirection,
+    /// Time since boot when the line was logged, `None` for old records
+    pub uptime: Option<Duration>,
+    pub text: &'static [u8],
+}
+
+/// Memory-mapped view of `len` bytes of the storage region
+fn flash_bytes(addr: u32, len: usize) -> &'static [u8] {
+    // SAFETY: the storage region is always-mapped internal flash
+    unsafe { core::slice::from_raw_parts(addr as *const u8, len) }
+}
+
+fn log_page_addr(page: u32) -> u32 {
+    LOG_START + page * PAGE_SIZE
+}
+
+/// Sequence number of a log page, `None` if the page is erased
+fn log_page_seq(page: u32) -> Option<u32> {
+    let header = flash_bytes(log_page_addr(page), LOG_HEADER_LEN as usize);
+    match u32::from_le_bytes([header[0], header[1], header[2], header[3]]) {
+        u32::MAX => None,
+        seq => Some(seq),
+    }
+}
+
+/// Index and sequence number of the most recently started log page
+fn newest_log_page() -> Option<(u32, u32)> {
+    (0..LOG_PAGES)
+        .filter_map(|page| log_page_seq(page).map(|seq| (page, seq)))
+        .max_by_key(|&(_, seq)| seq)
+}
+
+/// Index of the page where the ring starts: the one after the newest,
+/// which is the oldest or still erased
+fn oldest_log_page() -> u32 {
+    newest_log_page().map_or(0, |(page, _)| (page + 1) % LOG_PAGES)
+}
+
+/// Flash space taken by a record holding `len` bytes of payload
+fn log_record_len(len: usize) -> u32 {
+    // Padded to the 4-byte flash write size
+    ((LOG_RECORD_HEADER_LEN + len) as u32 + 3) & !3
+}
+
+/// Where the next session log record goes
+#[derive(Debug, Clone, Copy)]
+struct LogCursor {
+    page: u32,
+    seq: u32,
+    offset: u32,
+    /// Records stored across all pages, damaged ones included
+    records: u32,
+}
+
+impl LogCursor {
+    /// Find the end of the newest page, or `None` if the log is empty
+    fn locate() -> Option<Self> {
+        let (page, seq) = newest_log_page()?;
+        let records = (0..LOG_PAGES)
+            .filter(|&p| log_page_seq(p).is_some())
+            .map(|p| scan_log_page(p).0)
+            .sum();
+        Some(Self {
+            page,
+            seq,
+            offset: scan_log_page(page).1,
+            records,
+        })
+    }
+}
+
+/// Number of records on a log page and the offset just past the last one
+fn scan_log_page(page: u32) -> (u32, u32) {
+    let mut records = 0;
+    let mut offset = LOG_HEADER_LEN;
+    // Torn records are stepped over, not rewritten: their bytes aren't erased
+    while offset + LOG_RECORD_HEADER_LEN as u32 <= PAGE_SIZE {
+        let len = flash_bytes(log_page_addr(page) + offset, 1)[0];
+        if len == LOG_END {
+            break;
+        }
+        offset += log_record_len(len as usize);
+        records += 1;
+    }
+    (records, offset.min(PAGE_SIZE))
+}
+
+/// Session log records, oldest first
+///
+/// Records failing their checksum are skipped and counted in `corrupt`.
+pub struct LogRecords {
+    /// Pages left to visit, counting the current one
+    pages_left: u32,
+    page: u32,
+    offset: u32,
+    /// Records skipped so far because they were torn or damaged
+    pub corrupt: u32,
+}
+
+impl Iterator for LogRecords {
+    type Item = LogEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pages_left > 0 {
+            if log_page_seq(self.page).is_some()
+                && self.offset + (LOG_RECORD_HEADER_LEN as u32) <= PAGE_SIZE
+            {
+                let base = log_page_addr(self.page) + self.offset;
+                let header = flash_bytes(base, LOG_RECORD_HEADER_LEN);
+                if header[0] != LOG_END {
+                    let len = header[0] as usize;
+                    self.offset += log_record_len(len);
+                    let payload = flash_bytes(base + LOG_RECORD_HEADER_LEN as u32, len);
+                    let intact = header[2..] == log_checksum(header[0], header[1], payload);
+                    match LogDirection::from_tag(header[1]) {
+                        Some((direction, false)) if intact => {
+                            return Some(LogEntry {
+                                direction,
+                                uptime: None,
+                                text: payload,
+                            })
+                        }
+                        Some((direction, true)) if intact && len >= LOG_TIMESTAMP_LEN => {
+                            let (stamp, text) = payload.split_at(LOG_TIMESTAMP_LEN);
+                            let mut micros = [0; LOG_TIMESTAMP_LEN];
+                            micros.copy_from_slice(stamp);
+                            return Some(LogEntry {
+                                direction,
+                                uptime: Some(Duration::from_micros(u64::from_le_bytes(micros))),
+                                text,
+                            });
+                        }
+                        _ => self.corrupt += 1,
+                    }
+                    continue;
+                }
+            }
+            self.pages_left -= 1;
+            self.page = (self.page + 1) % LOG_PAGES;
+            self.offset = LOG_HEADER_LEN;
+        }
+        None
+    }
+}
+
+/// Iterate the session log from the oldest surviving record
+pub fn session_log() -> LogRecords {
+    let first = oldest_log_page();
+    LogRecords {
+        pages_left: LOG_PAGES,
+        page: first,
+        offset: LOG_HEADER_LEN,
+        corrupt: 0,
+    }
+}
+
+/// Cumulative counters that survive resets and reflashing
+///
+/// Every update appends a cell to the active page instead of rewriting it,
+/// so a page is erased only once per ~500 updates. When it fills, the
+/// latest values are copied to the other page, which is sealed last; a
+/// power loss mid-copy leaves the old page in charge.
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum Counter {
+    /// Boots of a CLI binary
+    PowerCycles,
+    /// Commands executed
+    Commands,
+    /// Successful BLE scans
+    BleScans,
+    /// Session log records dropped by the retention policy
+    LogDiscarded,
+}
+
+impl Counter {
+    /// Number of counters
+    pub const COUNT: usize = 4;
+    /// Every counter, in id order
+    pub const ALL: [Counter; Self::COUNT] = [
+        Counter::PowerCycles,
+        Counter::Commands,
+        Counter::BleScans,
+        Counter::LogDiscarded,
+    ];
+
+    fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Position in the array returned by [`counters`]
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Short name for display
+    pub fn label(self) -> &'static str {
+        match self {
+            Counter::PowerCycles => "Boots",
+            Counter::Commands => "Commands",
+            Counter::BleScans => "BLE scans",
+            Counter::LogDiscarded => "Log dropped",
+        }
+    }
+}
+
+/// Where the counters currently live and what they hold
+struct CounterPage {
+    index: usize,
+    seq: u32,
+    values: [u32; Counter::COUNT],
+    /// Offset of the first erased cell
+    next_offset: u32,
+}
+
+impl CounterPage {
+    /// Read the newest sealed counter page, `None` if no counters were ever stored
+    fn read() -> Option<Self> {
+        let (index, seq) = (0..COUNTER_PAGES.len())
+            .filter_map(|index| {
+                let header = flash_bytes(COUNTER_PAGES[index], COUNTER_HEADER_LEN as usize);
+                let seq = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+                (&header[4..] == COUNTER_MAGIC && seq != u32::MAX).then_some((index, seq))
+            })
+            .max_by_key(|&(_, seq)| seq)?;
+
+        let mut values = [0u32; Counter::COUNT];
+        let mut offset = COUNTER_HEADER_LEN;
+        while offset + COUNTER_CELL_LEN <= PAGE_SIZE {
+            let cell = flash_bytes(COUNTER_PAGES[index] + offset, COUNTER_CELL_LEN as usize);
+            if cell.iter().all(|&b| b == 0xFF) {
+                break;
+            }
+            let (id, check) = (cell[4] as usize, cell[5]);
+            if id < Counter::COUNT && check == !cell[4] {
+                values[id] = u32::from_le_bytes([cell[0], cell[1], cell[2], cell[3]]);
+            }
+            offset += COUNTER_CELL_LEN;
+        }
+        Some(Self {
+            index,
+            seq,
+            values,
+            next_offset: offset,
+        })
+    }
+}
+
+fn counter_cell(id: u8, value: u32) -> [u8; COUNTER_CELL_LEN as usize] {
+    let mut cell = [0u8; COUNTER_CELL_LEN as usize];
+    cell[..4].copy_from_slice(&value.to_le_bytes());
+    cell[4] = id;
+    cell[5] = !id;
+    cell
+}
+
+/// Current value of every lifetime counter, indexed by [`Counter`]
+pub fn counters() -> [u32; Counter::COUNT] {
+    CounterPage::read().map_or([0; Counter::COUNT], |page| page.values)
+}
+
+/// Size of the raw session log as seen by [`read_log_raw`]
+pub(crate) const LOG_SIZE: u32 = LOG_PAGES * PAGE_SIZE;
+
+/// Sequence number of the page at raw offset 0, 0 if the log is empty
+///
+/// It changes whenever the ring moves on, which tells a client that offsets
+/// it saved earlier no longer point at the same bytes.
+pub(crate) fn oldest_log_seq() -> u32 {
+    let first = oldest_log_page();
+    (0..LOG_PAGES)
+        .find_map(|i| log_page_seq((first + i) % LOG_PAGES))
+        .unwrap_or(0)
+}
+
+/// Copy raw session log bytes into `buf`, starting `offset` bytes in
+///
+/// The pages are laid end to end, oldest first. Each page is its 4-byte
+/// sequence number followed by records, padded to 4 bytes; a 0xFF length
+/// ends the page. Erased pages read as 0xFF. A record is:
+///
+/// | Bytes | Contents |
+/// |-------|----------|
+/// | 0     | Payload length |
+/// | 1     | Direction: `I` input or `O` output |
+/// | 2-3   | Low 16 bits of the CRC-32 of bytes 0-1 and the payload (little endian) |
+/// | 4-11  | Uptime in microseconds when logged (little endian) |
+/// | 12-   | Text |
+///
+/// The length counts the uptime and the text. Records written before
+/// timestamps were added are tagged `>` or `<` instead and their payload is
+/// only the text. Returns the number of bytes copied.
+pub(crate) fn read_log_raw(offset: u32, buf: &mut [u8]) -> usize {
+    let first = oldest_log_page();
+    let mut copied = 0;
+    while copied < buf.len() {
+        let pos = offset.saturating_add(copied as u32);
+        if pos >= LOG_SIZE {
+            break;
+        }
+        let page = (first + pos / PAGE_SIZE) % LOG_PAGES;
+        let in_page = pos % PAGE_SIZE;
+        let len = ((PAGE_SIZE - in_page) as usize).min(buf.len() - copied);
+        buf[copied..copied + len].copy_from_slice(flash_bytes(log_page_addr(page) + in_page, len));
+        copied += len;
+    }
+    copied
+}
+
+/// Result of a storage consistency check
+#[derive(Debug, Clone, Copy, Default, defmt::Format)]
+pub struct FsckReport {
+    /// Config slots holding an intact record
+    pub config_slots_ok: u8,
+    /// Damaged config slots that were erased
+    pub config_slots_erased: u8,
+    /// An unsealed record was rewritten with a sequence number and CRC
+    pub config_sealed: bool,
+    /// Intact session log records
+    pub log_records: u32,
+    /// Session log records failing their checksum, skipped when dumped
+    pub log_corrupt: u32,
+}
+
+impl FsckReport {
+    /// Whether anything had to be fixed or skipped
+    pub fn found_damage(&self) -> bool {
+        self.config_slots_erased > 0 || self.log_corrupt > 0
+    }
+}
+
+/// Writer for the persistent storage region
+pub struct Storage {
+    flash: Flash,
+    /// Session log write position, found on first use
+    log_cursor: Option<LogCursor>,
+}
+
+impl Storage {
+    /// Take ownership of the SoftDevice flash API (may only be called once)
+    pub fn new(sd: &Softdevice) -> Self {
+        Self {
+            flash: Flash::take(sd),
+            log_cursor: None,
+        }
+    }
+
+    /// Persist `config` in the slot not holding the newest record
+    ///
+    /// The previous record stays intact until the new one is sealed, so a
+    /// power loss mid-save falls back to it.
+    pub async fn save_config(&mut self, config: &Config) -> Result<(), StorageError> {
+        let slots = [Slot::read(0), Slot::read(1)];
+        let (target, seq) = match newest_slot(&slots) {
+            Some(newest) => (1 - newest, slots[newest].seq().unwrap_or(0) + 1),
+            None => (0, 1),
+        };
+
+        let mut slot = [0u8; SLOT_LEN];
+        slot[..CONFIG_RECORD_LEN].copy_from_slice(&encode_config(config));
+        slot[SLOT_RECORD_SPACE..SLOT_RECORD_SPACE + 4].copy_from_slice(&seq.to_le_bytes());
+        let crc = crc32(&slot[..SLOT_RECORD_SPACE + 4]);
+        slot[SLOT_RECORD_SPACE + 4..].copy_from_slice(&crc.to_le_bytes());
+
+        let addr = CONFIG_SLOTS[target];
+        self.flash
+            .erase(addr, addr + PAGE_SIZE)
+            .await
+            .map_err(|_| StorageError::Flash)?;
+        self.flash
+            .write(addr, &slot)
+            .await
+            .map_err(|_| StorageError::Flash)
+    }
+
+    /// Check both config slots and the session log, repairing what can be
+    ///
+    /// Damaged config slots are erased, and a record saved before slots were
+    /// sealed is rewritten sealed. Torn log records can't be rewritten in
+    /// place; they are counted and skipped.
+    pub async fn fsck(&mut self) -> Result<FsckReport, StorageError> {
+        let mut report = FsckReport::default();
+        let slots = [Slot::read(0), Slot::read(1)];
+        for (index, slot) in slots.iter().enumerate() {
+            match slot {
+                Slot::Unsealed(_) | Slot::Sealed(_, _) => report.config_slots_ok += 1,
+                Slot::Corrupt => {
+                    let addr = CONFIG_SLOTS[index];
+                    self.flash
+                        .erase(addr, addr + PAGE_SIZE)
+                        .await
+                        .map_err(|_| StorageError::Flash)?;
+                    report.config_slots_erased += 1;
+                }
+                Slot::Erased => {}
+            }
+        }
+        let sealed = slots.iter().any(|slot| matches!(slot, Slot::Sealed(_, _)));
+        if let (false, Some(config)) = (sealed, load_config()) {
+            self.save_config(&config).await?;
+            report.config_sealed = true;
+        }
+
+        let mut records = session_log();
+        report.log_records = records.by_ref().count() as u32;
+        report.log_corrupt = records.corrupt;
+        Ok(report)
+    }
+
+    /// Run [`fsck`](Self::fsck) at boot and log what it found
+    pub async fn recover(&mut self) {
+        match self.fsck().await {
+            Ok(report) if report.found_damage() || report.config_sealed => {
+                warn!("Storage repaired: {}", report)
+            }
+            Ok(_) => info!("Storage check passed"),
+            Err(e) => warn!("Storage check failed: {}", e),
+        }
+    }
+
+    /// Append one line to the session log, truncated to [`MAX_LOG_LINE`]
+    /// and stamped with the uptime
+    ///
+    /// Space is freed a page at a time. In overwrite mode, reaching the
+    /// entry limit or filling the ring erases the oldest page. Each page is a
+    /// quarter of the ring, so up to that many records are dropped at once:
+    /// the log can hold fewer than the limit, and a limit below one page's
+    /// worth only takes effect once a second page is used. In stop mode the new line
+    /// is dropped instead. Either way, dropped records go on the
+    /// [`Counter::LogDiscarded`] counter.
+    pub async fn append_log(
+        &mut self,
+        direction: LogDirection,
+        line: &str,
+        policy: LogPolicy,
+    ) -> Result<(), StorageError> {
+        let mut end = line.len().min(MAX_LOG_LINE);
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        let text = &line.as_bytes()[..end];
+        let payload_len = LOG_TIMESTAMP_LEN + text.len();
+        let record_len = log_record_len(payload_len);
+
+        let mut cursor = match self.log_cursor.or_else(LogCursor::locate) {
+            Some(cursor) => cursor,
+            None => self.start_log_page(0, 1, 0).await?,
+        };
+        let at_limit = |cursor: &LogCursor| {
+            policy.max_entries > 0 && cursor.records >= policy.max_entries as u32
+        };
+        let next = (cursor.page + 1) % LOG_PAGES;
+        let page_full = cursor.offset + record_len > PAGE_SIZE;
+
+        if policy.mode == LogMode::StopWhenFull
+            && (at_limit(&cursor) || (page_full && log_page_seq(next).is_some()))
+        {
+            self.log_cursor = Some(cursor);
+            self.count_discarded(1).await;
+            return Ok(());
+        }
+        if page_full {
+            let dropped = match log_page_seq(next) {
+                Some(_) => scan_log_page(next).0,
+                None => 0,
+            };
+            cursor = self
+                .start_log_page(next, cursor.seq + 1, cursor.records.saturating_sub(dropped))
+                .await?;
+            self.count_discarded(dropped).await;
+        }
+        if at_limit(&cursor) {
+            // The oldest page other than the one being written, if any
+            let oldest = (1..LOG_PAGES)
+                .map(|i| (cursor.page + i) % LOG_PAGES)
+                .find(|&p| log_page_seq(p).is_some());
+            if let Some(oldest) = oldest {
+                let dropped = scan_log_page(oldest).0;
+                let addr = log_page_addr(oldest);
+                self.log_cursor = None;
+                self.flash
+                    .erase(addr, addr + PAGE_SIZE)
+                    .await
+                    .map_err(|_| StorageError::Flash)?;
+                cursor.records = cursor.records.saturating_sub(dropped);
+                self.count_discarded(dropped).await;
+            }
+        }
+
+        let mut record = [LOG_END; LOG_RECORD_HEADER_LEN + LOG_TIMESTAMP_LEN + MAX_LOG_LINE];
+        let payload = &mut record[LOG_RECORD_HEADER_LEN..LOG_RECORD_HEADER_LEN + payload_len];
+        payload[..LOG_TIMESTAMP_LEN].copy_from_slice(&clock::uptime().as_micros().to_le_bytes());
+        payload[LOG_TIMESTAMP_LEN..].copy_from_slice(text);
+        let checksum = log_checksum(payload_len as u8, direction.tag(), payload);
+        record[0] = payload_len as u8;
+        record[1] = direction.tag();
+        record[2..4].copy_from_slice(&checksum);
+        self.flash
+            .write(
+                log_page_addr(cursor.page) + cursor.offset,
+                &record[..record_len as usize],
+            )
+            .await
+            .map_err(|_| StorageError::Flash)?;
+
+        cursor.offset += record_len;
+        cursor.records += 1;
+        self.log_cursor = Some(cursor);
+        Ok(())
+    }
+
+    /// Add dropped session log records to their lifetime counter
+    async fn count_discarded(&mut self, records: u32) {
+        if records > 0
+            && self
+                .add_to_counter(Counter::LogDiscarded, records)
+                .await
+                .is_err()
+        {
+            warn!("Discarded log record count not saved");
+        }
+    }
+
+    /// Add `amount` to a lifetime counter, returning the new total
+    pub async fn add_to_counter(
+        &mut self,
+        counter: Counter,
+        amount: u32,
+    ) -> Result<u32, StorageError> {
+        let page = match CounterPage::read() {
+            Some(page) if page.next_offset + COUNTER_CELL_LEN <= PAGE_SIZE => page,
+            full_or_none => {
+                let values = full_or_none
+                    .as_ref()
+                    .map_or([0; Counter::COUNT], |p| p.values);
+                let index = full_or_none.as_ref().map_or(0, |p| 1 - p.index);
+                let seq = full_or_none.as_ref().map_or(1, |p| p.seq + 1);
+                self.start_counter_page(index, seq, &values).await?
+            }
+        };
+
+        let value = page.values[counter.index()].saturating_add(amount);
+        self.flash
+            .write(
+                COUNTER_PAGES[page.index] + page.next_offset,
+                &counter_cell(counter.id(), value),
+            )
+            .await
+            .map_err(|_| StorageError::Flash)?;
+        Ok(value)
+    }
+
+    /// Copy `values` into a freshly erased counter page, then seal it as `seq`
+    async fn start_counter_page(
+        &mut self,
+        index: usize,
+        seq: u32,
+        values: &[u32; Counter::COUNT],
+    ) -> Result<CounterPage, StorageError> {
+        let addr = COUNTER_PAGES[index];
+        self.flash
+            .erase(addr, addr + PAGE_SIZE)
+            .await
+            .map_err(|_| StorageError::Flash)?;
+        let mut offset = COUNTER_HEADER_LEN;
+        for counter in Counter::ALL {
+            let value = values[counter.index()];
+            if value > 0 {
+                self.flash
+                    .write(addr + offset, &counter_cell(counter.id(), value))
+                    .await
+                    .map_err(|_| StorageError::Flash)?;
+                offset += COUNTER_CELL_LEN;
+            }
+        }
+        // Sealing last means a half-copied page is never read
+        let mut header = [0u8; COUNTER_HEADER_LEN as usize];
+        header[..4].copy_from_slice(&seq.to_le_bytes());
+        header[4..].copy_from_slice(COUNTER_MAGIC);
+        self.flash
+            .write(addr, &header)
+            .await
+            .map_err(|_| StorageError::Flash)?;
+        Ok(CounterPage {
+            index,
+            seq,
+            values: *values,
+            next_offset: offset,
+        })
+    }
+
+    /// Erase the whole session log
+    pub async fn clear_log(&mut self) -> Result<(), StorageError> {
+        self.log_cursor = None;
+        self.flash
+            .erase(LOG_START, log_page_addr(LOG_PAGES))
+            .await
+            .map_err(|_| StorageError::Flash)
+    }
+
+    /// Erase `page` and stamp it with `seq`, ready for records
+    ///
+    /// `records` is how many records the other pages still hold.
+    async fn start_log_page(
+        &mut self,
+        page: u32,
+        seq: u32,
+        records: u32,
+    ) -> Result<LogCursor, StorageError> {
+        // Forget the old position first so a failed erase is retried next time
+        self.log_cursor = None;
+        let addr = log_page_addr(page);
+        self.flash
+            .erase(addr, addr + PAGE_SIZE)
+            .await
+            .map_err(|_| StorageError::Flash)?;
+        self.flash
+            .write(addr, &seq.to_le_bytes())
+            .await
+            .map_err(|_| StorageError::Flash)?;
+        Ok(LogCursor {
+            page,
+            seq,
+            offset: LOG_HEADER_LEN,
+            records,
+        })
+    }
+}
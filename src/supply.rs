@@ -0,0 +1,83 @@
+//! Supply voltage monitoring
+//!
+//! The SAADC samples VDD directly (gain 1/6 against the 0.6 V internal
+//! reference, so 0-3.6 V full scale). When VDD drops below the configured
+//! threshold the low-supply flag is raised in the shared readings and
+//! activity LEDs stop flashing until the supply recovers.
+
+use crate::state::APP_STATE;
+use defmt::{info, warn};
+use embassy_nrf::bind_interrupts;
+use embassy_nrf::interrupt::{self, InterruptExt, Priority};
+use embassy_nrf::peripherals::SAADC;
+use embassy_nrf::saadc::{self, ChannelConfig, Saadc, VddInput};
+use embassy_time::{Duration, Timer};
+
+bind_interrupts!(struct Irqs {
+    SAADC => saadc::InterruptHandler;
+});
+
+/// Time between supply samples
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The supply must rise this far above the threshold to clear the low flag
+const HYSTERESIS_MV: u16 = 50;
+
+/// Full-scale voltage in mV for gain 1/6 and the 0.6 V reference
+const FULL_SCALE_MV: i32 = 3600;
+/// 12-bit conversion
+const FULL_SCALE_COUNTS: i32 = 4096;
+
+/// Convert a raw 12-bit VDD sample to millivolts
+pub fn counts_to_mv(raw: i16) -> u16 {
+    (raw.max(0) as i32 * FULL_SCALE_MV / FULL_SCALE_COUNTS) as u16
+}
+
+/// Whether `vdd_mv` is low, given the current state and a threshold (0 = off)
+pub fn is_low(vdd_mv: u16, threshold_mv: u16, currently_low: bool) -> bool {
+    if threshold_mv == 0 {
+        false
+    } else if currently_low {
+        vdd_mv < threshold_mv + HYSTERESIS_MV
+    } else {
+        vdd_mv < threshold_mv
+    }
+}
+
+/// Whether non-essential activity (status LEDs) should be suppressed
+pub fn low_supply() -> bool {
+    APP_STATE.readings().low_supply
+}
+
+/// Sample VDD forever, publishing it to [`APP_STATE`]
+#[embassy_executor::task]
+pub async fn monitor_task(saadc: SAADC) {
+    // The SoftDevice reserves priorities 0, 1 and 4
+    interrupt::SAADC.set_priority(Priority::P2);
+    let channel = ChannelConfig::single_ended(VddInput);
+    let mut adc = Saadc::new(saadc, Irqs, saadc::Config::default(), [channel]);
+    adc.calibrate().await;
+
+    loop {
+        let mut sample = [0i16; 1];
+        adc.sample(&mut sample).await;
+        let vdd_mv = counts_to_mv(sample[0]);
+        let threshold_mv = APP_STATE.config().low_supply_mv;
+
+        let was_low = low_supply();
+        let low = is_low(vdd_mv, threshold_mv, was_low);
+        if low != was_low {
+            if low {
+                warn!("Supply low: {} mV (threshold {} mV)", vdd_mv, threshold_mv);
+            } else {
+                info!("Supply recovered: {} mV", vdd_mv);
+            }
+        }
+        APP_STATE.update_readings(|r| {
+            r.vdd_mv = Some(vdd_mv);
+            r.low_supply = low;
+        });
+
+        Timer::after(POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,173 @@
+//! Software (bit-bang) UART receiver
+//!
+//! Receives 8N1 frames on any GPIO by timestamping edges instead of
+//! generating a sample clock: a falling edge from idle marks the start bit,
+//! and each bit is read from the line level at its centre, reconstructed
+//! from the surrounding edges. Being input-only, it can tap a line passively.
+//!
+//! Timestamps come from embassy-time, which ticks at 32.768 kHz on the
+//! RTC, so reception is limited to [`MAX_BAUD`]. The fuzz build keeps the
+//! limits and the decoder but not the GPIO receiver.
+
+#[cfg(not(feature = "fuzz"))]
+use embassy_nrf::gpio::Input;
+#[cfg(not(feature = "fuzz"))]
+use embassy_time::{with_timeout, Duration, Instant};
+
+/// Lowest supported baud rate
+pub const MIN_BAUD: u32 = 50;
+/// Highest baud rate the 30.5 us timestamp resolution decodes reliably
+pub const MAX_BAUD: u32 = 2400;
+
+/// Start bit, 8 data bits and the stop bit
+const FRAME_BITS: u8 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum FrameError {
+    /// The line was high again at the centre of the start bit (glitch)
+    Noise,
+    /// The stop bit was low
+    Framing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    /// Receiving a frame whose start edge was at `start_us`
+    Frame {
+        start_us: u64,
+        next_bit: u8,
+        data: u8,
+    },
+}
+
+/// Edge-driven 8N1 frame decoder, independent of any hardware
+pub struct Decoder {
+    bit_us: u64,
+    level: bool,
+    state: State,
+}
+
+impl Decoder {
+    /// A decoder for `baud`, with the line idle (high)
+    pub fn new(baud: u32) -> Self {
+        Self {
+            bit_us: 1_000_000 / baud.max(1) as u64,
+            level: true,
+            state: State::Idle,
+        }
+    }
+
+    /// Duration of one bit in microseconds
+    pub fn bit_us(&self) -> u64 {
+        self.bit_us
+    }
+
+    /// The line changed to `level` at `time_us`
+    ///
+    /// Returns a byte (or error) when this edge completes a frame.
+    pub fn edge(&mut self, time_us: u64, level: bool) -> Option<Result<u8, FrameError>> {
+        // Bit centres before this edge saw the previous level
+        let result = self.advance(time_us);
+        self.level = level;
+        if self.state == State::Idle && !level {
+            self.state = State::Frame {
+                start_us: time_us,
+                next_bit: 0,
+                data: 0,
+            };
+        }
+        result
+    }
+
+    /// No edge has occurred up to `now_us`
+    ///
+    /// Call this when the line goes quiet, to finish a frame whose last
+    /// bits didn't produce edges.
+    pub fn idle(&mut self, now_us: u64) -> Option<Result<u8, FrameError>> {
+        self.advance(now_us)
+    }
+
+    /// Sample every bit centre before `until_us` using the current level
+    fn advance(&mut self, until_us: u64) -> Option<Result<u8, FrameError>> {
+        let State::Frame {
+            start_us,
+            mut next_bit,
+            mut data,
+        } = self.state
+        else {
+            return None;
+        };
+
+        let mut result = None;
+        while next_bit < FRAME_BITS {
+            let centre_us = start_us + self.bit_us * next_bit as u64 + self.bit_us / 2;
+            if centre_us >= until_us {
+                break;
+            }
+            match next_bit {
+                0 if self.level => result = Some(Err(FrameError::Noise)),
+                0 => {}
+                1..=8 => data |= (self.level as u8) << (next_bit - 1),
+                _ if self.level => result = Some(Ok(data)),
+                _ => result = Some(Err(FrameError::Framing)),
+            }
+            if result.is_some() {
+                self.state = State::Idle;
+                return result;
+            }
+            next_bit += 1;
+        }
+
+        self.state = State::Frame {
+            start_us,
+            next_bit,
+            data,
+        };
+        result
+    }
+}
+
+/// Counts from one [`receive`] run
+#[derive(Debug, Clone, Copy, Default, defmt::Format)]
+pub struct RxStats {
+    pub bytes: u32,
+    pub noise_errors: u32,
+    pub framing_errors: u32,
+    /// Bytes dropped because `buf` was full
+    pub overflow: u32,
+}
+
+/// Receive 8N1 bytes on `pin` at `baud` for `duration`, appending to `buf`
+#[cfg(not(feature = "fuzz"))]
+pub async fn receive<const N: usize>(
+    pin: &mut Input<'_>,
+    baud: u32,
+    duration: Duration,
+    buf: &mut heapless::Vec<u8, N>,
+) -> RxStats {
+    let mut decoder = Decoder::new(baud);
+    let mut stats = RxStats::default();
+    // Long enough for any frame in progress to finish
+    let quiet = Duration::from_micros(decoder.bit_us() * FRAME_BITS as u64);
+    let deadline = Instant::now() + duration;
+
+    while Instant::now() < deadline {
+        let result = match with_timeout(quiet, pin.wait_for_any_edge()).await {
+            Ok(()) => decoder.edge(Instant::now().as_micros(), pin.is_high()),
+            Err(_) => decoder.idle(Instant::now().as_micros()),
+        };
+        match result {
+            Some(Ok(byte)) => {
+                stats.bytes += 1;
+                if buf.push(byte).is_err() {
+                    stats.overflow += 1;
+                }
+            }
+            Some(Err(FrameError::Noise)) => stats.noise_errors += 1,
+            Some(Err(FrameError::Framing)) => stats.framing_errors += 1,
+            None => {}
+        }
+    }
+    stats
+}
@@ -0,0 +1,91 @@
+//! System control helpers
+//!
+//! Orderly shutdown before a software reset so that the last console output
+//! and RTT log lines are not lost, and GPREGRET-based selection of the mode
+//! the device boots into after the reset.
+
+use cortex_m::peripheral::SCB;
+use defmt::info;
+use embassy_nrf::pac;
+use embassy_time::{Duration, Timer};
+
+/// Time allowed for the UART to shift out the final response bytes
+const RESET_FLUSH_DELAY_MS: u64 = 50;
+
+/// GPREGRET value that makes the Nordic DFU bootloader stay in DFU mode
+pub const GPREGRET_BOOTLOADER_DFU: u8 = 0xB1;
+/// GPREGRET value requesting a safe-mode boot of the application
+pub const GPREGRET_SAFE_MODE: u8 = 0xA5;
+
+/// Mode to boot into after a reset
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub enum BootMode {
+    /// Regular application start
+    Normal,
+    /// Application start without autorun and background tasks
+    Safe,
+    /// Stay in the DFU bootloader
+    Bootloader,
+}
+
+impl BootMode {
+    fn gpregret(self) -> u8 {
+        match self {
+            BootMode::Normal => 0,
+            BootMode::Safe => GPREGRET_SAFE_MODE,
+            BootMode::Bootloader => GPREGRET_BOOTLOADER_DFU,
+        }
+    }
+}
+
+/// Read and clear the boot mode requested before the last reset
+///
+/// Must be called before the SoftDevice is enabled, as it accesses the
+/// POWER peripheral directly.
+pub fn take_boot_mode() -> BootMode {
+    let value = pac::POWER.gpregret().read().gpregret();
+    pac::POWER.gpregret().write(|w| w.set_gpregret(0));
+
+    match value {
+        GPREGRET_SAFE_MODE => BootMode::Safe,
+        _ => BootMode::Normal,
+    }
+}
+
+fn write_gpregret(value: u8) {
+    // With the SoftDevice running the POWER peripheral is restricted,
+    // so the request has to go through the SoftDevice API
+    #[cfg(feature = "ble")]
+    {
+        let mut enabled: u8 = 0;
+        unsafe { nrf_softdevice::raw::sd_softdevice_is_enabled(&mut enabled) };
+        if enabled != 0 {
+            unsafe {
+                nrf_softdevice::raw::sd_power_gpregret_clr(0, 0xFF);
+                nrf_softdevice::raw::sd_power_gpregret_set(0, value as u32);
+            }
+            return;
+        }
+    }
+
+    pac::POWER.gpregret().write(|w| w.set_gpregret(value));
+}
+
+/// Flush pending output and reset the system into `mode`
+pub async fn graceful_reset(mode: BootMode) -> ! {
+    info!("System: preparing for reset into {:?} mode", mode);
+
+    // Let the last UART transfer leave the shift register
+    Timer::after(Duration::from_millis(RESET_FLUSH_DELAY_MS)).await;
+
+    write_gpregret(mode.gpregret());
+
+    info!("System: resetting now");
+    defmt::flush();
+    SCB::sys_reset()
+}
+
+/// Reset immediately without flushing anything
+pub fn immediate_reset() -> ! {
+    SCB::sys_reset()
+}
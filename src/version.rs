@@ -0,0 +1,31 @@
+//! Firmware version and build information
+//!
+//! The values are captured by build.rs at compile time, so a board can
+//! always report exactly which source and configuration it is running.
+
+/// Crate version from Cargo.toml
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// `git describe` output, with a `-dirty` suffix for uncommitted changes
+pub const GIT_HASH: &str = env!("BUILD_GIT_HASH");
+
+/// When the build script last ran ("YYYY-MM-DD HH:MM UTC")
+pub const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
+
+/// Enabled cargo features, comma separated
+pub const FEATURES: &str = env!("BUILD_FEATURES");
+
+/// Linker memory layout selected by build.rs (e.g. "memory-softdevice.x")
+pub const MEMORY_LAYOUT: &str = env!("BUILD_MEMORY_LAYOUT");
+
+/// Cargo profile ("debug" or "release")
+pub const PROFILE: &str = env!("BUILD_PROFILE");
+
+/// Firmware name and version as shown to users
+pub const FIRMWARE: &str = concat!(
+    "nRF52840-DK CLI v",
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("BUILD_GIT_HASH"),
+    ")"
+);
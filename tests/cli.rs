@@ -0,0 +1,716 @@
+//! On-target tests for the CLI parser and output helpers
+//!
+//! Run with a board attached (SoftDevice flashed):
+//! `cargo test --test cli --no-default-features --features cli`
+
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use panic_probe as _;
+
+#[defmt_test::tests]
+mod tests {
+    use defmt::{assert, assert_eq};
+    use heapless::String;
+    use nrf52840_dk_template::cli::{
+        args::ArgProblem, output, parser::CommandParser, registry, AddrSetting, CaptureView,
+        CliCommand, MetaField, MetaSetting, ResetMode, ScanCfgSetting, SessionLogSetting,
+    };
+    use nrf52840_dk_template::clock::DateTime;
+    use nrf52840_dk_template::fmt_util;
+    use nrf52840_dk_template::led::LedId;
+    use nrf52840_dk_template::state::{Config, LogMode, ScanTiming};
+    use nrf52840_dk_template::supply;
+    use nrf52840_dk_template::swuart::{Decoder, FrameError};
+
+    #[test]
+    fn parses_simple_commands() {
+        assert!(matches!(
+            CommandParser::parse_command("help"),
+            CliCommand::Help
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("version"),
+            CliCommand::Version
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("build_info"),
+            CliCommand::BuildInfo
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("status"),
+            CliCommand::Status
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("uptime"),
+            CliCommand::Uptime
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("date"),
+            CliCommand::Date(None)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("date 1717000000"),
+            CliCommand::Date(Some(1717000000))
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("button"),
+            CliCommand::Button
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("temp"),
+            CliCommand::Temp
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("show"),
+            CliCommand::ShowAll
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("show all"),
+            CliCommand::ShowAll
+        ));
+    }
+
+    #[test]
+    fn blank_input_is_empty() {
+        assert!(matches!(
+            CommandParser::parse_command(""),
+            CliCommand::Empty
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("   "),
+            CliCommand::Empty
+        ));
+    }
+
+    #[test]
+    fn unknown_command_is_reported() {
+        match CommandParser::parse_command("frobnicate") {
+            CliCommand::Unknown(cmd) => assert_eq!(cmd.as_str(), "frobnicate"),
+            _ => defmt::panic!("expected Unknown"),
+        }
+    }
+
+    #[test]
+    fn led_commands_validate_number() {
+        assert!(matches!(
+            CommandParser::parse_command("led_on 3"),
+            CliCommand::LedOn(led) if led.number() == 3
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("led_off 4"),
+            CliCommand::LedOff(led) if led.number() == 4
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("led_on 0"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("led_on 5"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("led_off x"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("led_on"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn led_id_rejects_out_of_range() {
+        assert!(LedId::new(0).is_none());
+        assert!(LedId::new(5).is_none());
+        let led = LedId::new(1).unwrap();
+        assert_eq!(led.index(), 0);
+        assert!(!led.is_cli_controllable());
+        assert!(LedId::new(4).unwrap().is_cli_controllable());
+    }
+
+    #[test]
+    fn bt_scan_time_range() {
+        assert!(matches!(
+            CommandParser::parse_command("bt_scan"),
+            CliCommand::BtScan(None)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("bt_scan 15"),
+            CliCommand::BtScan(Some(15))
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("bt_scan 0"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("bt_scan 61"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn reset_modes() {
+        assert!(matches!(
+            CommandParser::parse_command("reset"),
+            CliCommand::Reset(ResetMode::Graceful)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("reset now"),
+            CliCommand::Reset(ResetMode::Now)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("reset bootloader"),
+            CliCommand::Reset(ResetMode::Bootloader)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("reset safe"),
+            CliCommand::Reset(ResetMode::Safe)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("reset later"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn echo_joins_arguments() {
+        match CommandParser::parse_command("echo  hello   world ") {
+            CliCommand::Echo(text) => assert_eq!(text.as_str(), "hello world"),
+            _ => defmt::panic!("expected Echo"),
+        }
+    }
+
+    #[test]
+    fn watch_and_time_wrappers() {
+        match CommandParser::parse_command("watch status 5") {
+            CliCommand::Watch(cmd, interval) => {
+                assert_eq!(cmd.as_str(), "status");
+                assert_eq!(interval, 5);
+            }
+            _ => defmt::panic!("expected Watch"),
+        }
+        assert!(matches!(
+            CommandParser::parse_command("watch uptime"),
+            CliCommand::Watch(_, 2)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("watch reset"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("watch temp 0"),
+            CliCommand::InvalidArgs(_)
+        ));
+        match CommandParser::parse_command("watch 5 show all") {
+            CliCommand::Watch(cmd, interval) => {
+                assert_eq!(cmd.as_str(), "show all");
+                assert_eq!(interval, 5);
+            }
+            _ => defmt::panic!("expected Watch"),
+        }
+        match CommandParser::parse_command("watch show all 3") {
+            CliCommand::Watch(cmd, interval) => {
+                assert_eq!(cmd.as_str(), "show all");
+                assert_eq!(interval, 3);
+            }
+            _ => defmt::panic!("expected Watch"),
+        }
+        assert!(matches!(
+            CommandParser::parse_command("watch show everything"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("watch supply low 3000"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("watch 5"),
+            CliCommand::InvalidArgs(_)
+        ));
+
+        match CommandParser::parse_command("time bt_scan 5") {
+            CliCommand::Time(line) => assert_eq!(line.as_str(), "bt_scan 5"),
+            _ => defmt::panic!("expected Time"),
+        }
+        assert!(matches!(
+            CommandParser::parse_command("time"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn bench_uart_arguments() {
+        assert!(matches!(
+            CommandParser::parse_command("bench_uart 4096"),
+            CliCommand::BenchUart(4096, false)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("bench_uart 100 loop"),
+            CliCommand::BenchUart(100, true)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("bench_uart 0"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("bench_uart"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn raw_transfer_arguments() {
+        match CommandParser::parse_command("echo_hex 00 1b7f FF") {
+            CliCommand::EchoHex(bytes) => assert_eq!(bytes.as_slice(), &[0x00, 0x1b, 0x7f, 0xff]),
+            _ => defmt::panic!("expected EchoHex"),
+        }
+        assert!(matches!(
+            CommandParser::parse_command("echo_hex 1b7"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("echo_hex zz"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("loopback 256"),
+            CliCommand::Loopback(256)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("loopback 0"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn session_log_arguments() {
+        assert!(matches!(
+            CommandParser::parse_command("session_log"),
+            CliCommand::SessionLog(SessionLogSetting::Show)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("session_log on"),
+            CliCommand::SessionLog(SessionLogSetting::On)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("session_log clear"),
+            CliCommand::SessionLog(SessionLogSetting::Clear)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("session_log maybe"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("session_dump"),
+            CliCommand::SessionDump
+        ));
+        match CommandParser::parse_command("log_policy stop 500") {
+            CliCommand::LogPolicy(Some(policy)) => {
+                assert_eq!(policy.mode, LogMode::StopWhenFull);
+                assert_eq!(policy.max_entries, 500);
+            }
+            _ => defmt::panic!("expected LogPolicy"),
+        }
+        assert!(matches!(
+            CommandParser::parse_command("log_policy"),
+            CliCommand::LogPolicy(None)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("log_policy keep"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("storage_fsck"),
+            CliCommand::StorageFsck
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("lifetime"),
+            CliCommand::Lifetime
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("mem_budget"),
+            CliCommand::MemBudget
+        ));
+    }
+
+    #[test]
+    fn meta_arguments() {
+        assert!(matches!(
+            CommandParser::parse_command("meta"),
+            CliCommand::Meta(MetaSetting::Show)
+        ));
+        match CommandParser::parse_command("meta site  Pump house 3 ") {
+            CliCommand::Meta(MetaSetting::Set(MetaField::Site, text)) => {
+                assert_eq!(text.as_str(), "Pump house 3")
+            }
+            _ => defmt::panic!("expected Meta Set"),
+        }
+        assert!(matches!(
+            CommandParser::parse_command("meta note clear"),
+            CliCommand::Meta(MetaSetting::Clear(MetaField::Note))
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("meta serial"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("meta owner x"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn supply_arguments() {
+        assert!(matches!(
+            CommandParser::parse_command("supply"),
+            CliCommand::Supply(None)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("supply low 2400"),
+            CliCommand::Supply(Some(2400))
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("supply off"),
+            CliCommand::Supply(Some(0))
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("supply low 900"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn supply_threshold_hysteresis() {
+        assert_eq!(supply::counts_to_mv(4096), 3600);
+        assert_eq!(supply::counts_to_mv(-5), 0);
+        assert!(!supply::is_low(2000, 0, false));
+        assert!(supply::is_low(2399, 2400, false));
+        // Stays low until 50 mV above the threshold
+        assert!(supply::is_low(2420, 2400, true));
+        assert!(!supply::is_low(2450, 2400, true));
+    }
+
+    #[test]
+    fn pinspect_arguments() {
+        assert!(matches!(
+            CommandParser::parse_command("pinspect P0.13"),
+            CliCommand::Pinspect(13)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("pinspect 1.02"),
+            CliCommand::Pinspect(34)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("pinspect P1.16"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("pinspect"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn swuart_rx_arguments() {
+        assert!(matches!(
+            CommandParser::parse_command("swuart_rx P0.03 1200"),
+            CliCommand::SwUartRx(3, 1200, 5, CaptureView::Hex)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("swuart_rx 1.02 2400 30"),
+            CliCommand::SwUartRx(34, 2400, 30, CaptureView::Hex)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("swuart_rx P0.03 1200 text"),
+            CliCommand::SwUartRx(3, 1200, 5, CaptureView::Text)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("swuart_rx P0.03 1200 10 text"),
+            CliCommand::SwUartRx(3, 1200, 10, CaptureView::Text)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("swuart_rx P0.03 1200 10 ascii"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("swuart_rx P1.16 1200"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("swuart_rx P0.03 9600"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn swuart_decodes_edges() {
+        // 1200 baud: 833 us per bit. 'A' (0x41) is 0 1000 0010 1 on the wire
+        let mut decoder = Decoder::new(1200);
+        let bit = decoder.bit_us();
+        assert_eq!(decoder.edge(1000, false), None); // start bit
+        assert_eq!(decoder.edge(1000 + bit, true), None); // bit 0 = 1
+        assert_eq!(decoder.edge(1000 + 2 * bit, false), None); // bits 1-5 = 0
+        assert_eq!(decoder.edge(1000 + 7 * bit, true), None); // bit 6 = 1
+        assert_eq!(decoder.edge(1000 + 8 * bit, false), None); // bit 7 = 0
+        assert_eq!(decoder.edge(1000 + 9 * bit, true), None); // stop bit
+        assert_eq!(decoder.idle(1000 + 12 * bit), Some(Ok(0x41)));
+
+        // A glitch shorter than half a bit is not a start bit
+        assert_eq!(decoder.edge(20_000, false), None);
+        assert_eq!(decoder.edge(20_100, true), None);
+        assert_eq!(decoder.idle(30_000), Some(Err(FrameError::Noise)));
+    }
+
+    #[test]
+    fn color_setting() {
+        assert!(matches!(
+            CommandParser::parse_command("color"),
+            CliCommand::Color(None)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("color on"),
+            CliCommand::Color(Some(true))
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("color off"),
+            CliCommand::Color(Some(false))
+        ));
+    }
+
+    #[test]
+    fn prompt_setting() {
+        assert!(matches!(
+            CommandParser::parse_command("prompt"),
+            CliCommand::Prompt(None)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("prompt compact"),
+            CliCommand::Prompt(Some(true))
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("prompt full"),
+            CliCommand::Prompt(Some(false))
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("prompt short"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn bt_scancfg_timing() {
+        assert!(matches!(
+            CommandParser::parse_command("bt_scancfg"),
+            CliCommand::BtScanCfg(ScanCfgSetting::Show)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("bt_scancfg default"),
+            CliCommand::BtScanCfg(ScanCfgSetting::Default)
+        ));
+        match CommandParser::parse_command("bt_scancfg 100 25") {
+            CliCommand::BtScanCfg(ScanCfgSetting::Set(timing)) => {
+                assert_eq!(timing, ScanTiming::new(100, 25).unwrap())
+            }
+            _ => defmt::panic!("expected BtScanCfg"),
+        }
+        // Window longer than interval, and out of range values
+        assert!(ScanTiming::new(50, 100).is_none());
+        assert!(ScanTiming::new(20000, 100).is_none());
+        assert!(ScanTiming::new(100, 2).is_none());
+        assert!(matches!(
+            CommandParser::parse_command("bt_scancfg 100"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn bt_name_and_addr() {
+        assert!(matches!(
+            CommandParser::parse_command("bt_name"),
+            CliCommand::BtName(None)
+        ));
+        match CommandParser::parse_command("bt_name bench dk") {
+            CliCommand::BtName(Some(name)) => assert_eq!(name.as_str(), "bench dk"),
+            _ => defmt::panic!("expected BtName"),
+        }
+        assert!(matches!(
+            CommandParser::parse_command("bt_name 0123456789abcdef0123456789abcdef"),
+            CliCommand::InvalidArgs(_)
+        ));
+
+        assert!(matches!(
+            CommandParser::parse_command("bt_addr"),
+            CliCommand::BtAddr(AddrSetting::Show)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("bt_addr factory"),
+            CliCommand::BtAddr(AddrSetting::Factory)
+        ));
+        match CommandParser::parse_command("bt_addr C0:11:22:33:44:55") {
+            CliCommand::BtAddr(AddrSetting::Set(addr)) => {
+                assert_eq!(addr, [0x55, 0x44, 0x33, 0x22, 0x11, 0xc0])
+            }
+            _ => defmt::panic!("expected BtAddr"),
+        }
+        // Top two bits clear: not a static random address
+        assert!(matches!(
+            CommandParser::parse_command("bt_addr 12:11:22:33:44:55"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("bt_addr c0:11:22:33:44"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn autocomplete_matches_prefix() {
+        let matches = CommandParser::autocomplete("led");
+        assert_eq!(matches.len(), 2);
+        assert!(matches.contains(&"led_on"));
+        assert!(matches.contains(&"led_off"));
+        assert_eq!(CommandParser::autocomplete("zzz").len(), 0);
+        // Nothing typed yet offers every command
+        assert_eq!(
+            CommandParser::autocomplete("").len(),
+            registry::COMMANDS.len()
+        );
+    }
+
+    #[test]
+    fn registry_drives_parser() {
+        for (i, spec) in registry::COMMANDS.iter().enumerate() {
+            // Names are unique, complete to themselves and are never unknown
+            assert!(registry::COMMANDS[..i]
+                .iter()
+                .all(|other| other.name != spec.name));
+            assert!(CommandParser::autocomplete(spec.name).contains(&spec.name));
+            assert!(!matches!(
+                CommandParser::parse_command(spec.name),
+                CliCommand::Unknown(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn argument_errors_quote_the_argument() {
+        match CommandParser::parse_command("bt_scan 99") {
+            CliCommand::InvalidArgs(error) => {
+                assert_eq!(error.command, "bt_scan");
+                assert_eq!(error.arg.as_deref(), Some("99"));
+                assert_eq!(error.problem, ArgProblem::OutOfRange(1, 60));
+                assert_eq!(error.usage(), "[time]");
+            }
+            _ => defmt::panic!("expected InvalidArgs"),
+        }
+        match CommandParser::parse_command("swuart_rx P0.03") {
+            CliCommand::InvalidArgs(error) => {
+                assert!(error.arg.is_none());
+                assert_eq!(error.problem, ArgProblem::Missing("baud"));
+            }
+            _ => defmt::panic!("expected InvalidArgs"),
+        }
+        match CommandParser::parse_command("color blue") {
+            CliCommand::InvalidArgs(error) => {
+                assert_eq!(error.arg.as_deref(), Some("blue"));
+                assert_eq!(error.problem, ArgProblem::NotAChoice);
+            }
+            _ => defmt::panic!("expected InvalidArgs"),
+        }
+        match CommandParser::parse_command("led_on 3 now") {
+            CliCommand::InvalidArgs(error) => {
+                assert_eq!(error.arg.as_deref(), Some("now"));
+                assert_eq!(error.problem, ArgProblem::Unexpected);
+            }
+            _ => defmt::panic!("expected InvalidArgs"),
+        }
+        assert!(matches!(
+            CommandParser::parse_command("bt_scancfg default 5"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("echo_hex +f"),
+            CliCommand::InvalidArgs(_)
+        ));
+        assert!(matches!(
+            CommandParser::parse_command("bt_addr C0:11:22:33:44:+5"),
+            CliCommand::InvalidArgs(_)
+        ));
+    }
+
+    #[test]
+    fn quoted_text_arguments() {
+        match CommandParser::parse_command("bt_name \" bench \"") {
+            CliCommand::BtName(Some(name)) => assert_eq!(name.as_str(), " bench "),
+            _ => defmt::panic!("expected BtName"),
+        }
+        // Quoting stores the word instead of clearing the field
+        match CommandParser::parse_command("meta note \"clear\"") {
+            CliCommand::Meta(MetaSetting::Set(MetaField::Note, text)) => {
+                assert_eq!(text.as_str(), "clear")
+            }
+            _ => defmt::panic!("expected Meta Set"),
+        }
+    }
+
+    #[test]
+    fn label_column_alignment() {
+        let mut s: String<64> = String::new();
+        output::push_label(&mut s, "LEDs").unwrap();
+        assert_eq!(s.as_str(), "  LEDs:       ");
+        assert_eq!(s.len(), 2 + output::LABEL_WIDTH);
+
+        let mut s: String<16> = String::new();
+        output::push_padded(&mut s, "ab", 4).unwrap();
+        assert_eq!(s.as_str(), "ab  ");
+    }
+
+    #[test]
+    fn numeric_formatting() {
+        let mut s: String<32> = String::new();
+        fmt_util::write_num(&mut s, 0).unwrap();
+        s.push(' ').unwrap();
+        fmt_util::write_signed(&mut s, -42).unwrap();
+        s.push(' ').unwrap();
+        fmt_util::write_hex_byte(&mut s, 0x0f).unwrap();
+        s.push(' ').unwrap();
+        fmt_util::write_fixed(&mut s, -1205, 3).unwrap();
+        assert_eq!(s.as_str(), "0 -42 0f -1.205");
+
+        let mut s: String<32> = String::new();
+        fmt_util::write_datetime(&mut s, &DateTime::from_unix(0)).unwrap();
+        s.push(' ').unwrap();
+        // 2024-02-29 is a leap day
+        fmt_util::write_datetime(&mut s, &DateTime::from_unix(1_709_218_861)).unwrap();
+        assert_eq!(s.as_str(), "1970-01-01 00:00:00 2024-02-29 15:01:01");
+
+        let mut s: String<32> = String::new();
+        fmt_util::write_escaped(&mut s, b"U3\x0f\\A\r").unwrap();
+        assert_eq!(s.as_str(), "U3\\x0f\\\\A\\x0d");
+
+        let mut s: String<16> = String::new();
+        fmt_util::write_temperature(&mut s, -0.25).unwrap();
+        assert_eq!(s.as_str(), "-0.3°C");
+
+        let mut s: String<16> = String::new();
+        fmt_util::write_percent(&mut s, 39, 40).unwrap();
+        s.push(' ').unwrap();
+        fmt_util::write_percent(&mut s, 1, 3).unwrap();
+        s.push(' ').unwrap();
+        fmt_util::write_percent(&mut s, 0, 0).unwrap();
+        assert_eq!(s.as_str(), "97.5% 33.3% n/a");
+
+        let mut s: String<2> = String::new();
+        assert!(fmt_util::write_num(&mut s, 123).is_err());
+    }
+
+    #[test]
+    fn config_defaults() {
+        let config = Config::new();
+        assert_eq!(config.scan_time_secs, 10);
+        assert!(config.color);
+        assert!(config.site.is_empty() && config.meter_serial.is_empty() && config.note.is_empty());
+    }
+}